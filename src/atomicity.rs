@@ -0,0 +1,116 @@
+use crate::block_budget;
+use crate::compute_budget;
+use crate::owned::{Bundle, BundleOptions};
+use crate::report::{ProcessingReport, Rejection, Stage};
+use crate::types::*;
+
+// Which transactions of a bundle land
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InclusionPlan {
+    pub included: Vec<usize>,
+    pub dropped: Vec<usize>,
+    pub compute_units: u64,
+}
+
+fn validate_options(options: &BundleOptions, transaction_count: usize) -> Result<(), &'static str> {
+    let reverting = &options.reverting_transactions;
+
+    if reverting.iter().any(|&idx| idx >= transaction_count) {
+        return Err("Reverting transaction index out of range");
+    }
+
+    if (1..reverting.len()).any(|i| reverting[..i].contains(&reverting[i])) {
+        return Err("Duplicate reverting transaction index");
+    }
+
+    Ok(())
+}
+
+// Work out what lands under the bundle's options. Transactions an atomic
+// bundle can't lose must all fit the remaining block compute; the rest are
+// taken in bundle order while they fit. A bundle that can't land at all is
+// rejected.
+pub fn plan_inclusion(bundle: &Bundle) -> Result<InclusionPlan, Rejection> {
+    plan_inclusion_within(bundle, bundle.options.remaining_block_compute_units)
+}
+
+// `plan_inclusion` against an explicit remaining block compute, where None
+// means unlimited
+pub fn plan_inclusion_within(bundle: &Bundle, remaining_compute_units: Option<u64>) -> Result<InclusionPlan, Rejection> {
+    let options = &bundle.options;
+    let count = bundle.transactions.len();
+
+    validate_options(options, count)
+        .map_err(|e| Rejection::new(Stage::Atomicity, ERROR_INVALID_BUNDLE, None, e))?;
+
+    let compute_units: Vec<u64> = bundle.transactions.iter().map(compute_budget::compute_units).collect();
+    let required = |idx: usize| options.atomic && !options.reverting_transactions.contains(&idx);
+
+    let budget = match remaining_compute_units {
+        Some(budget) => budget,
+        None => {
+            return Ok(InclusionPlan {
+                included: (0..count).collect(),
+                dropped: Vec::new(),
+                compute_units: compute_units.iter().fold(0u64, |total, &cu| total.saturating_add(cu)),
+            });
+        }
+    };
+
+    let required_units = (0..count)
+        .filter(|&idx| required(idx))
+        .fold(0u64, |total, idx| total.saturating_add(compute_units[idx]));
+    if required_units > budget {
+        let reason = format!(
+            "Atomic bundle needs {} CU but only {} remain in the block",
+            required_units, budget
+        );
+        return Err(Rejection::new(Stage::Atomicity, ERROR_BLOCK_LIMIT_EXCEEDED, None, reason));
+    }
+
+    let mut plan = InclusionPlan {
+        compute_units: required_units,
+        ..Default::default()
+    };
+    for (idx, &units) in compute_units.iter().enumerate() {
+        if required(idx) {
+            plan.included.push(idx);
+        } else if plan.compute_units.saturating_add(units) <= budget {
+            plan.compute_units += units;
+            plan.included.push(idx);
+        } else {
+            plan.dropped.push(idx);
+        }
+    }
+
+    if plan.included.is_empty() {
+        let reason = format!("No transaction fits the {} CU remaining in the block", budget);
+        return Err(Rejection::new(Stage::Atomicity, ERROR_BLOCK_LIMIT_EXCEEDED, None, reason));
+    }
+
+    Ok(plan)
+}
+
+// Enforce the bundle's inclusion rules and record what lands
+pub fn check_bundle(bundle: &Bundle, report: &mut ProcessingReport) -> i32 {
+    report.enter(Stage::Atomicity);
+
+    // Without a host-supplied figure, use what the block tracker has left
+    let remaining = bundle.options.remaining_block_compute_units
+        .or_else(|| block_budget::remaining_for(report, bundle.metadata.slot));
+
+    match plan_inclusion_within(bundle, remaining) {
+        Ok(plan) => {
+            if !plan.dropped.is_empty() {
+                log::debug!("Dropping transactions {:?} that don't fit the block", plan.dropped);
+            }
+            report.included_transactions = plan.included;
+            report.dropped_transactions = plan.dropped;
+            SUCCESS
+        }
+        Err(rejection) => {
+            log::error!("{}", rejection.reason);
+            report.record(rejection)
+        }
+    }
+}
@@ -0,0 +1,232 @@
+// Command-line front end to the plugin: load a bundle from a fixture file and
+// run it through validation, fee estimation, a processing pipeline or the
+// bundle analyses, printing the result as JSON.
+
+use base64::{engine::general_purpose, Engine as _};
+use relay_bam_plugin::trace::TraceBundle;
+use relay_bam_plugin::*;
+use serde_json::json;
+use std::collections::HashMap;
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const USAGE: &str = "\
+Usage: relay-bam <command> <bundle-file> [options]
+
+Commands:
+  validate        Check bundle structure, signatures and transactions
+  estimate-fee    Estimate the plugin fee and the bundle's value
+  process         Run the bundle through a processing pipeline
+  stats           Compute, conflict, oracle, MEV and institutional analysis
+
+Bundle files are either JSON (a trace bundle: slot, leader_pubkey,
+plugin_fees, tip_amount and base64 wire transactions) or text with one base64
+wire-format transaction per line.
+
+Options:
+  --pipeline v1|v2|v3   Pipeline for process and estimate-fee (default v3)
+  --config <file>       Plugin config JSON, as passed to plugin_init
+  --slot <n>            Slot for wire files (default 1)
+  --plugin-fees <n>     Plugin fees for wire files (default 0)
+  --tip <n>             Tip amount for wire files (default 0)
+  --leader <base58>     Leader pubkey for wire files
+                        (default 4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi)
+";
+
+// Exit codes: rejected bundles and bad input are told apart for scripts
+const EXIT_REJECTED: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+
+struct Args {
+    command: String,
+    file: String,
+    options: HashMap<String, String>,
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut positional = Vec::new();
+    let mut options = HashMap::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.strip_prefix("--") {
+            Some(name) => {
+                let value = iter.next().ok_or_else(|| format!("Missing value for --{}", name))?;
+                options.insert(name.to_string(), value.clone());
+            }
+            None => positional.push(arg.clone()),
+        }
+    }
+
+    match positional.as_slice() {
+        [command, file] => Ok(Args { command: command.clone(), file: file.clone(), options }),
+        _ => Err("Expected a command and a bundle file".to_string()),
+    }
+}
+
+fn option<T: std::str::FromStr>(args: &Args, name: &str, default: T) -> Result<T, String> {
+    match args.options.get(name) {
+        Some(value) => value.parse().map_err(|_| format!("Invalid --{}: {}", name, value)),
+        None => Ok(default),
+    }
+}
+
+fn pipeline(args: &Args) -> Result<Pipeline, String> {
+    let name = args.options.get("pipeline").map(String::as_str).unwrap_or("v3");
+    serde_json::from_value(json!(name)).map_err(|_| format!("Unknown pipeline: {}", name))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn load_bundle(args: &Args) -> Result<Bundle, String> {
+    let contents = std::fs::read_to_string(&args.file).map_err(|e| format!("{}: {}", args.file, e))?;
+
+    if contents.trim_start().starts_with('{') {
+        let fixture: TraceBundle = serde_json::from_str(&contents).map_err(|e| format!("{}: {}", args.file, e))?;
+        let mut bundle = fixture.to_bundle()?;
+        if bundle.metadata.timestamp == 0 {
+            bundle.metadata.timestamp = now();
+        }
+        return Ok(bundle);
+    }
+
+    let transactions = contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| general_purpose::STANDARD.decode(line).map_err(|_| "Invalid base64 transaction".to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let leader = match args.options.get("leader") {
+        Some(leader) => leader.parse::<Pubkey>()
+            .map_err(|e| format!("Invalid --leader {}: {}", leader, e))?
+            .bytes,
+        None => [1u8; 32],
+    };
+
+    let metadata = BundleMetadata {
+        slot: option(args, "slot", 1)?,
+        timestamp: now(),
+        leader_pubkey: leader,
+        plugin_fees: option(args, "plugin-fees", 0)?,
+        tip_amount: option(args, "tip", 0)?,
+    };
+
+    Ok(wire::decode_bundle(&transactions, metadata)?)
+}
+
+fn apply_config(args: &Args) -> Result<(), String> {
+    match args.options.get("config") {
+        Some(path) => {
+            let config = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+            configure(&config).map_err(|e| format!("{}: {}", path, e))
+        }
+        None => Ok(()),
+    }
+}
+
+fn validate(bundle: &Bundle) -> (serde_json::Value, bool) {
+    let mut report = ProcessingReport::new(Pipeline::V1);
+    let result = validation::validate_bundle(bundle, &mut report);
+
+    let output = json!({
+        "valid": result == SUCCESS,
+        "result": result,
+        "rejection": report.rejection,
+    });
+    (output, result == SUCCESS)
+}
+
+fn estimate(bundle: &Bundle, pipeline: Pipeline) -> serde_json::Value {
+    json!({
+        "pipeline": pipeline,
+        "fee": estimate_fee(bundle, pipeline),
+        "breakdown": fees::calculate_fee_breakdown(bundle),
+        "value": fees::estimate_bundle_value(bundle),
+    })
+}
+
+fn stats(bundle: &Bundle) -> serde_json::Value {
+    let mut output = json!({
+        "transactions": bundle.transactions.len(),
+        "signatures": bundle.transactions.iter().map(|tx| tx.signature()).collect::<Vec<_>>(),
+        "blockhashes": bundle.transactions.iter().map(|tx| tx.blockhash()).collect::<Vec<_>>(),
+        "stats": processing::get_bundle_stats(bundle),
+        "execution_plan": execution_plan(bundle),
+        "swaps": mev::detect_swaps(bundle).len(),
+        "estimated_mev": mev::estimate_bundle_mev(bundle).total,
+        "sandwiches": sandwich::detect_sandwiches(bundle),
+    });
+
+    #[cfg(feature = "oracle")]
+    {
+        let injection_points = oracle::extract_price_injection_points(bundle);
+        let mut dependent: Vec<usize> = injection_points.iter().map(|point| point.transaction_index).collect();
+        dependent.dedup();
+        output["price_injection_points"] = json!(injection_points.len());
+        output["oracle_dependent_transactions"] = json!(dependent);
+    }
+
+    #[cfg(feature = "institutional")]
+    {
+        let sequencer = institutional::InstitutionalSequencer::new(&institutional::get_institutional_config());
+        let market_makers: Vec<usize> = bundle.transactions.iter()
+            .enumerate()
+            .filter(|(_, tx)| sequencer.is_market_maker_transaction(tx))
+            .map(|(idx, _)| idx)
+            .collect();
+        output["market_maker_transactions"] = json!(market_makers);
+
+        let detector = institutional::CrossChainDetector::new();
+        let arbitrage: Vec<usize> = bundle.transactions.iter()
+            .enumerate()
+            .filter(|(_, tx)| detector.has_arbitrage_potential(tx))
+            .map(|(idx, _)| idx)
+            .collect();
+        output["arbitrage_candidates"] = json!(arbitrage);
+    }
+
+    output
+}
+
+fn run(args: &Args) -> Result<bool, String> {
+    let pipeline = pipeline(args)?;
+    apply_config(args)?;
+    let bundle = load_bundle(args)?;
+
+    let (output, accepted) = match args.command.as_str() {
+        "validate" => validate(&bundle),
+        "estimate-fee" => (estimate(&bundle, pipeline), true),
+        "process" => {
+            let report = process_with_report(&bundle, pipeline);
+            let accepted = report.is_success();
+            (json!(report), accepted)
+        }
+        "stats" => (stats(&bundle), true),
+        command => return Err(format!("Unknown command: {}", command)),
+    };
+
+    let output = serde_json::to_string_pretty(&output).map_err(|e| e.to_string())?;
+    println!("{}", output);
+    Ok(accepted)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let result = parse_args(&args).and_then(|args| run(&args));
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(EXIT_REJECTED),
+        Err(e) => {
+            eprintln!("relay-bam: {}", e);
+            eprintln!("Run `relay-bam --help` for usage");
+            ExitCode::from(EXIT_USAGE)
+        }
+    }
+}
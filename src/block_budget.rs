@@ -0,0 +1,226 @@
+use crate::compute_budget;
+use crate::conflicts::AccountLocks;
+use crate::owned::Bundle;
+use crate::report::{ProcessingReport, Rejection, Stage};
+use crate::types::*;
+use crate::PLUGIN_STATE;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Solana's per-block compute limits
+pub const MAX_BLOCK_COMPUTE_UNITS: u64 = 48_000_000;
+pub const MAX_WRITABLE_ACCOUNT_COMPUTE_UNITS: u64 = 12_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlockLimits {
+    // When off, usage is still tracked but bundles are never refused for it
+    pub enforce: bool,
+    pub max_block_compute_units: u64,
+    pub max_writable_account_compute_units: u64,
+}
+
+impl Default for BlockLimits {
+    fn default() -> Self {
+        Self {
+            enforce: false,
+            max_block_compute_units: MAX_BLOCK_COMPUTE_UNITS,
+            max_writable_account_compute_units: MAX_WRITABLE_ACCOUNT_COMPUTE_UNITS,
+        }
+    }
+}
+
+impl BlockLimits {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.max_block_compute_units == 0 || self.max_writable_account_compute_units == 0 {
+            return Err("Block limits must be positive");
+        }
+        if self.max_writable_account_compute_units > self.max_block_compute_units {
+            return Err("Account limit exceeds block limit");
+        }
+        Ok(())
+    }
+}
+
+// Compute a bundle adds to a block: its total and, per writable account,
+// the compute of the transactions write-locking it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockUsage {
+    pub compute_units: u64,
+    pub writable_accounts: HashMap<Pubkey, u64>,
+}
+
+impl BlockUsage {
+    pub fn for_transactions(bundle: &Bundle, transactions: &[usize]) -> Self {
+        let mut usage = Self::default();
+
+        for tx in transactions.iter().filter_map(|&idx| bundle.transactions.get(idx)) {
+            let units = compute_budget::compute_units(tx);
+            usage.compute_units = usage.compute_units.saturating_add(units);
+
+            // Validation already resolved every lookup table
+            if let Ok(locks) = AccountLocks::for_transaction(tx) {
+                for account in locks.writable {
+                    let account_units = usage.writable_accounts.entry(account).or_insert(0);
+                    *account_units = account_units.saturating_add(units);
+                }
+            }
+        }
+
+        usage
+    }
+}
+
+// Usage of the current slot's block. Lives in `PluginState`; the per-account
+// map stays out of the serialized state to keep it small.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlockBudget {
+    pub slot: u64,
+    pub bundles: u64,
+    pub compute_units: u64,
+    #[serde(skip)]
+    pub writable_accounts: HashMap<Pubkey, u64>,
+}
+
+impl BlockBudget {
+    // Compute units left for a bundle in `slot`
+    pub fn remaining_compute_units(&self, slot: u64, limits: &BlockLimits) -> u64 {
+        match slot.cmp(&self.slot) {
+            std::cmp::Ordering::Less => 0,
+            std::cmp::Ordering::Equal => limits.max_block_compute_units.saturating_sub(self.compute_units),
+            std::cmp::Ordering::Greater => limits.max_block_compute_units,
+        }
+    }
+
+    // Whether `usage` still fits the block for `slot`
+    pub fn check(&self, slot: u64, usage: &BlockUsage, limits: &BlockLimits) -> Result<(), String> {
+        if slot < self.slot {
+            return Err(format!("Slot {} has already finished (current slot {})", slot, self.slot));
+        }
+        let current = slot == self.slot;
+
+        let used = if current { self.compute_units } else { 0 };
+        if used.saturating_add(usage.compute_units) > limits.max_block_compute_units {
+            return Err(format!(
+                "Bundle needs {} CU but only {} remain in the block",
+                usage.compute_units,
+                limits.max_block_compute_units.saturating_sub(used)
+            ));
+        }
+
+        // Deterministic order so the reported account doesn't vary
+        let mut accounts: Vec<(&Pubkey, &u64)> = usage.writable_accounts.iter().collect();
+        accounts.sort_by_key(|(account, _)| account.bytes);
+        for (account, &units) in accounts {
+            let used = if current { self.writable_accounts.get(account).copied().unwrap_or(0) } else { 0 };
+            if used.saturating_add(units) > limits.max_writable_account_compute_units {
+                return Err(format!(
+                    "Account {} would exceed its {} CU write-lock limit",
+                    account,
+                    limits.max_writable_account_compute_units
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Add an accepted bundle's usage, starting a fresh block when the slot
+    // advances. Usage for a finished slot is ignored.
+    pub fn record(&mut self, slot: u64, usage: &BlockUsage) {
+        if slot < self.slot {
+            return;
+        }
+        if slot > self.slot {
+            self.slot = slot;
+            self.bundles = 0;
+            self.compute_units = 0;
+            self.writable_accounts.clear();
+        }
+
+        self.bundles += 1;
+        self.compute_units = self.compute_units.saturating_add(usage.compute_units);
+        for (account, &units) in &usage.writable_accounts {
+            let account_units = self.writable_accounts.entry(*account).or_insert(0);
+            *account_units = account_units.saturating_add(units);
+        }
+    }
+
+    // Check and record in one step; without enforcement it only records
+    pub fn try_commit(&mut self, slot: u64, usage: &BlockUsage, limits: &BlockLimits) -> Result<(), String> {
+        if limits.enforce {
+            self.check(slot, usage, limits)?;
+        }
+        self.record(slot, usage);
+        Ok(())
+    }
+}
+
+// A block of the caller's own that a bundle is checked against instead of
+// the slot's live one, as when building a block or replaying a trace
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SimulatedBlock {
+    pub budget: BlockBudget,
+    pub limits: BlockLimits,
+}
+
+// Compute units left in the block for `slot`, or None when limits aren't
+// enforced
+pub fn remaining_compute_units(slot: u64) -> Option<u64> {
+    let state = PLUGIN_STATE.lock().ok()?;
+    let limits = &state.config.block_limits;
+    limits.enforce.then(|| state.block_budget.remaining_compute_units(slot, limits))
+}
+
+// Compute units left in the block the report's bundle is checked against
+pub(crate) fn remaining_for(report: &ProcessingReport, slot: u64) -> Option<u64> {
+    match &report.block {
+        Some(block) => block.limits.enforce.then(|| block.budget.remaining_compute_units(slot, &block.limits)),
+        None => remaining_compute_units(slot),
+    }
+}
+
+// Refuse a bundle whose landing transactions would overrun the block. Runs
+// after the atomicity stage, which decides what lands.
+pub fn check_bundle(bundle: &Bundle, report: &mut ProcessingReport) -> i32 {
+    report.enter(Stage::BlockLimits);
+
+    let usage = BlockUsage::for_transactions(bundle, &report.included_transactions);
+    let result = match &report.block {
+        Some(block) if block.limits.enforce => block.budget.check(bundle.metadata.slot, &usage, &block.limits),
+        Some(_) => Ok(()),
+        None => match PLUGIN_STATE.lock() {
+            Ok(state) if state.config.block_limits.enforce => {
+                state.block_budget.check(bundle.metadata.slot, &usage, &state.config.block_limits)
+            }
+            _ => Ok(()),
+        },
+    };
+
+    match result {
+        Ok(()) => SUCCESS,
+        Err(reason) => {
+            log::error!("{}", reason);
+            report.reject(ERROR_BLOCK_LIMIT_EXCEEDED, None, reason)
+        }
+    }
+}
+
+// Record a processed bundle against its slot's block. Concurrent bundles
+// may both pass `check_bundle`, so the limits are checked again here under
+// the state lock.
+pub fn commit_bundle(bundle: &Bundle, included: &[usize]) -> Result<(), Rejection> {
+    let usage = BlockUsage::for_transactions(bundle, included);
+
+    let mut state = match PLUGIN_STATE.lock() {
+        Ok(state) => state,
+        Err(_) => return Ok(()),
+    };
+    let limits = state.config.block_limits.clone();
+
+    state.block_budget.try_commit(bundle.metadata.slot, &usage, &limits).map_err(|reason| {
+        log::error!("{}", reason);
+        Rejection::new(Stage::BlockLimits, ERROR_BLOCK_LIMIT_EXCEEDED, None, reason)
+    })
+}
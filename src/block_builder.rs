@@ -0,0 +1,370 @@
+use crate::block_budget::{BlockBudget, BlockLimits, BlockUsage, SimulatedBlock};
+use crate::compute_budget;
+use crate::conflicts::AccountLocks;
+use crate::owned::Bundle;
+use crate::report::{Rejection, Stage};
+use crate::tips;
+use crate::types::*;
+use crate::{evaluate_in, Pipeline};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+// Above this many accepted candidates the exact search gives way to greedy
+// packing
+pub const MAX_OPTIMAL_CANDIDATES: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackingStrategy {
+    // Take bundles by value per CU while they fit
+    #[default]
+    Greedy,
+    // Search for the highest-value set of bundles that fits
+    Optimal,
+}
+
+// A bundle that made it into the block. Figures cover only the transactions
+// that land.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackedBundle {
+    // Position in the candidate queue
+    pub candidate: usize,
+    pub compute_units: u64,
+    pub priority_fees: u64,
+    pub tips: u64,
+    pub plugin_fees: u64,
+}
+
+impl PackedBundle {
+    pub fn value(&self) -> u64 {
+        self.priority_fees
+            .saturating_add(self.tips)
+            .saturating_add(self.plugin_fees)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RejectedBundle {
+    pub candidate: usize,
+    pub rejection: Rejection,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockReport {
+    pub slot: u64,
+    pub strategy: PackingStrategy,
+    // Packed bundles in execution order
+    pub packed: Vec<PackedBundle>,
+    // Rejected bundles in queue order
+    pub rejected: Vec<RejectedBundle>,
+    pub compute_units: u64,
+    pub total_priority_fees: u64,
+    pub total_tips: u64,
+    pub total_plugin_fees: u64,
+    pub total_value: u64,
+}
+
+// Assembles a block for one slot from competing bundles. Each candidate goes
+// through the plugin pipeline first; the accepted ones are packed by value
+// per CU so that no two packed bundles contend for an account and the block
+// stays within its limits. The limits always apply here, whatever their
+// `enforce` flag says.
+//
+// Building is a simulation: candidates are evaluated against an empty block
+// with the builder's limits, without claiming block space in the plugin
+// state or counting them as processed.
+#[derive(Debug, Clone)]
+pub struct BlockBuilder {
+    slot: u64,
+    pipeline: Pipeline,
+    strategy: PackingStrategy,
+    limits: BlockLimits,
+    candidates: Vec<Bundle>,
+}
+
+// An accepted bundle waiting to be packed
+struct Candidate {
+    packed: PackedBundle,
+    usage: BlockUsage,
+    locks: AccountLocks,
+}
+
+impl BlockBuilder {
+    pub fn new(slot: u64) -> Self {
+        Self {
+            slot,
+            pipeline: Pipeline::V3,
+            strategy: PackingStrategy::default(),
+            limits: BlockLimits::default(),
+            candidates: Vec::new(),
+        }
+    }
+
+    pub fn with_pipeline(mut self, pipeline: Pipeline) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: PackingStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: BlockLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_bundle(mut self, bundle: Bundle) -> Self {
+        self.push(bundle);
+        self
+    }
+
+    pub fn push(&mut self, bundle: Bundle) {
+        self.candidates.push(bundle);
+    }
+
+    pub fn build(&self) -> BlockReport {
+        let mut rejected = Vec::new();
+        let mut candidates = Vec::new();
+
+        for (index, bundle) in self.candidates.iter().enumerate() {
+            match self.evaluate_candidate(index, bundle) {
+                Ok(candidate) => candidates.push(candidate),
+                Err(rejection) => rejected.push(RejectedBundle { candidate: index, rejection }),
+            }
+        }
+
+        candidates.sort_by(|a, b| by_density(&a.packed, &b.packed));
+
+        let selected = match self.strategy {
+            PackingStrategy::Optimal if candidates.len() <= MAX_OPTIMAL_CANDIDATES => {
+                optimal_selection(&candidates, &self.limits)
+            }
+            PackingStrategy::Optimal => {
+                log::debug!("{} candidates is too many for an exact search, packing greedily", candidates.len());
+                vec![false; candidates.len()]
+            }
+            PackingStrategy::Greedy => vec![false; candidates.len()],
+        };
+
+        // Selected bundles go in first; the rest fill what's left by density,
+        // and whatever can't fit is reported with the reason
+        let mut block = Block::new(self.slot, &self.limits);
+        let (chosen, remaining): (Vec<_>, Vec<_>) = candidates.iter()
+            .enumerate()
+            .partition(|(position, _)| selected[*position]);
+        for (position, candidate) in chosen {
+            block.add(position, candidate);
+        }
+        for (position, candidate) in remaining {
+            match block.admit(candidate) {
+                Ok(()) => block.add(position, candidate),
+                Err(rejection) => rejected.push(RejectedBundle {
+                    candidate: candidate.packed.candidate,
+                    rejection,
+                }),
+            }
+        }
+
+        // Sorted positions put the block in density order
+        block.packed.sort_unstable();
+        rejected.sort_by_key(|rejected| rejected.candidate);
+
+        let packed: Vec<PackedBundle> = block.packed.iter().map(|&position| candidates[position].packed.clone()).collect();
+        let sum = |field: fn(&PackedBundle) -> u64| packed.iter().fold(0u64, |total, bundle| total.saturating_add(field(bundle)));
+
+        BlockReport {
+            slot: self.slot,
+            strategy: self.strategy,
+            compute_units: sum(|bundle| bundle.compute_units),
+            total_priority_fees: sum(|bundle| bundle.priority_fees),
+            total_tips: sum(|bundle| bundle.tips),
+            total_plugin_fees: sum(|bundle| bundle.plugin_fees),
+            total_value: sum(PackedBundle::value),
+            packed,
+            rejected,
+        }
+    }
+
+    fn evaluate_candidate(&self, index: usize, bundle: &Bundle) -> Result<Candidate, Rejection> {
+        if bundle.metadata.slot != self.slot {
+            let reason = format!("Bundle targets slot {}, not {}", bundle.metadata.slot, self.slot);
+            return Err(Rejection::new(Stage::BlockPacking, ERROR_INVALID_BUNDLE, None, reason));
+        }
+
+        let block = SimulatedBlock {
+            budget: BlockBudget { slot: self.slot, ..Default::default() },
+            limits: BlockLimits { enforce: true, ..self.limits.clone() },
+        };
+        let report = evaluate_in(bundle, self.pipeline, block);
+        if !report.is_success() {
+            return Err(report.rejection.unwrap_or_else(|| {
+                Rejection::new(Stage::BlockPacking, report.result, None, "Rejected by the pipeline")
+            }));
+        }
+
+        let included = &report.included_transactions;
+        let landing = || included.iter().filter_map(|&idx| bundle.transactions.get(idx));
+
+        let mut locks = AccountLocks::default();
+        for tx_locks in landing().filter_map(|tx| AccountLocks::for_transaction(tx).ok()) {
+            locks.writable.extend(tx_locks.writable);
+            locks.readonly.extend(tx_locks.readonly);
+        }
+        let AccountLocks { writable, readonly } = &mut locks;
+        readonly.retain(|key| !writable.contains(key));
+
+        // Without tip accounts only the declared tip is known
+        let tip_accounts = tips::configured_tip_accounts();
+        let tips = if tip_accounts.is_empty() {
+            bundle.metadata.tip_amount
+        } else {
+            let per_transaction = tips::tips_per_transaction(bundle, &tip_accounts);
+            included.iter().fold(0u64, |total, &idx| total.saturating_add(per_transaction[idx]))
+        };
+
+        let usage = BlockUsage::for_transactions(bundle, included);
+        Ok(Candidate {
+            packed: PackedBundle {
+                candidate: index,
+                compute_units: usage.compute_units,
+                priority_fees: landing().fold(0u64, |total, tx| total.saturating_add(compute_budget::priority_fee(tx))),
+                tips,
+                plugin_fees: bundle.metadata.plugin_fees,
+            },
+            usage,
+            locks,
+        })
+    }
+}
+
+// Higher value per CU first, then queue order. Compared by cross-multiplying
+// so zero-CU bundles sort first.
+fn by_density(a: &PackedBundle, b: &PackedBundle) -> Ordering {
+    let a_side = a.value() as u128 * b.compute_units as u128;
+    let b_side = b.value() as u128 * a.compute_units as u128;
+    a_side.cmp(&b_side).reverse().then(a.candidate.cmp(&b.candidate))
+}
+
+// Whether a bundle fits an empty block
+fn fits_alone(usage: &BlockUsage, limits: &BlockLimits) -> bool {
+    usage.compute_units <= limits.max_block_compute_units
+        && usage.writable_accounts.values().all(|&units| units <= limits.max_writable_account_compute_units)
+}
+
+// Branch and bound over the density-sorted candidates. Packed bundles never
+// share a writable account, so each one's account usage only has to fit on
+// its own and block compute is the one shared resource.
+fn optimal_selection(candidates: &[Candidate], limits: &BlockLimits) -> Vec<bool> {
+    let count = candidates.len();
+    let mut search = Search {
+        candidates,
+        fits_alone: candidates.iter().map(|candidate| fits_alone(&candidate.usage, limits)).collect(),
+        conflicts: (0..count)
+            .map(|i| (0..count).map(|j| i != j && candidates[i].locks.conflicts_with(&candidates[j].locks)).collect())
+            .collect(),
+        capacity: limits.max_block_compute_units,
+        current: vec![false; count],
+        best: vec![false; count],
+        best_value: 0,
+    };
+    search.explore(0, 0, 0);
+    search.best
+}
+
+struct Search<'a> {
+    candidates: &'a [Candidate],
+    fits_alone: Vec<bool>,
+    conflicts: Vec<Vec<bool>>,
+    capacity: u64,
+    current: Vec<bool>,
+    best: Vec<bool>,
+    best_value: u64,
+}
+
+impl Search<'_> {
+    fn explore(&mut self, next: usize, value: u64, compute_units: u64) {
+        if value > self.best_value {
+            self.best_value = value;
+            self.best.clone_from(&self.current);
+        }
+        if next == self.candidates.len() || self.bound(next, value, compute_units) <= self.best_value {
+            return;
+        }
+
+        let candidate = &self.candidates[next].packed;
+        let fits = self.fits_alone[next]
+            && compute_units.saturating_add(candidate.compute_units) <= self.capacity
+            && !(0..next).any(|other| self.current[other] && self.conflicts[next][other]);
+        if fits {
+            self.current[next] = true;
+            self.explore(next + 1, value.saturating_add(candidate.value()), compute_units + candidate.compute_units);
+            self.current[next] = false;
+        }
+
+        self.explore(next + 1, value, compute_units);
+    }
+
+    // Best value reachable from here ignoring conflicts: the fractional
+    // knapsack over the remaining candidates, which are already in density
+    // order
+    fn bound(&self, next: usize, value: u64, compute_units: u64) -> u64 {
+        let mut remaining = self.capacity.saturating_sub(compute_units);
+        let mut bound = value;
+
+        for (idx, candidate) in self.candidates.iter().enumerate().skip(next) {
+            if !self.fits_alone[idx] {
+                continue;
+            }
+            let candidate = &candidate.packed;
+            if candidate.compute_units <= remaining {
+                remaining -= candidate.compute_units;
+                bound = bound.saturating_add(candidate.value());
+            } else {
+                let fraction = (candidate.value() as u128 * remaining as u128).div_ceil(candidate.compute_units as u128);
+                return bound.saturating_add(fraction.min(u64::MAX as u128) as u64);
+            }
+        }
+
+        bound
+    }
+}
+
+// The block being filled; `packed` holds positions in the sorted candidate
+// list
+struct Block<'a> {
+    slot: u64,
+    limits: &'a BlockLimits,
+    budget: BlockBudget,
+    packed: Vec<usize>,
+    locks: Vec<(usize, &'a AccountLocks)>,
+}
+
+impl<'a> Block<'a> {
+    fn new(slot: u64, limits: &'a BlockLimits) -> Self {
+        Self {
+            slot,
+            limits,
+            budget: BlockBudget::default(),
+            packed: Vec::new(),
+            locks: Vec::new(),
+        }
+    }
+
+    fn admit(&self, candidate: &Candidate) -> Result<(), Rejection> {
+        if let Some((other, _)) = self.locks.iter().find(|(_, locks)| locks.conflicts_with(&candidate.locks)) {
+            let reason = format!("Contends for accounts with bundle {}", other);
+            return Err(Rejection::new(Stage::BlockPacking, ERROR_LOCK_CONFLICT, None, reason));
+        }
+
+        self.budget.check(self.slot, &candidate.usage, self.limits)
+            .map_err(|reason| Rejection::new(Stage::BlockPacking, ERROR_BLOCK_LIMIT_EXCEEDED, None, reason))
+    }
+
+    fn add(&mut self, position: usize, candidate: &'a Candidate) {
+        self.budget.record(self.slot, &candidate.usage);
+        self.locks.push((candidate.packed.candidate, &candidate.locks));
+        self.packed.push(position);
+    }
+}
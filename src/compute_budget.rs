@@ -0,0 +1,176 @@
+use crate::owned::{Instruction, Tx};
+use crate::types::*;
+
+// ComputeBudget111111111111111111111111111111
+pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey = Pubkey {
+    bytes: [
+        3, 6, 70, 111, 229, 33, 23, 50, 255, 236, 173, 186, 114, 195, 155, 231,
+        188, 140, 229, 187, 197, 247, 18, 107, 44, 67, 155, 58, 64, 0, 0, 0,
+    ],
+};
+
+// Runtime limits
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+pub const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+pub const MIN_HEAP_FRAME_BYTES: u32 = 32 * 1024;
+pub const MAX_HEAP_FRAME_BYTES: u32 = 256 * 1024;
+pub const HEAP_FRAME_GRANULARITY: u32 = 1024;
+
+pub const MICRO_LAMPORTS_PER_LAMPORT: u64 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComputeBudgetInstruction {
+    RequestHeapFrame(u32),
+    SetComputeUnitLimit(u32),
+    // Micro-lamports per compute unit
+    SetComputeUnitPrice(u64),
+    SetLoadedAccountsDataSizeLimit(u32),
+}
+
+impl ComputeBudgetInstruction {
+    // Borsh layout: one discriminator byte, then a little-endian argument.
+    // Discriminator 0 is the long-removed RequestUnits and isn't accepted.
+    pub fn decode(data: &[u8]) -> Result<Self, &'static str> {
+        let (&tag, args) = data.split_first().ok_or("Empty compute budget instruction")?;
+
+        match tag {
+            1 => Ok(Self::RequestHeapFrame(read_u32(args)?)),
+            2 => Ok(Self::SetComputeUnitLimit(read_u32(args)?)),
+            3 => Ok(Self::SetComputeUnitPrice(read_u64(args)?)),
+            4 => Ok(Self::SetLoadedAccountsDataSizeLimit(read_u32(args)?)),
+            _ => Err("Unknown compute budget instruction"),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(9);
+        match *self {
+            Self::RequestHeapFrame(bytes) => {
+                data.push(1);
+                data.extend_from_slice(&bytes.to_le_bytes());
+            }
+            Self::SetComputeUnitLimit(units) => {
+                data.push(2);
+                data.extend_from_slice(&units.to_le_bytes());
+            }
+            Self::SetComputeUnitPrice(micro_lamports) => {
+                data.push(3);
+                data.extend_from_slice(&micro_lamports.to_le_bytes());
+            }
+            Self::SetLoadedAccountsDataSizeLimit(bytes) => {
+                data.push(4);
+                data.extend_from_slice(&bytes.to_le_bytes());
+            }
+        }
+        data
+    }
+}
+
+fn read_u32(args: &[u8]) -> Result<u32, &'static str> {
+    let bytes: [u8; 4] = args.try_into().map_err(|_| "Invalid compute budget instruction data")?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(args: &[u8]) -> Result<u64, &'static str> {
+    let bytes: [u8; 8] = args.try_into().map_err(|_| "Invalid compute budget instruction data")?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+// What a transaction's compute budget instructions request. The
+// `requested_*` fields record which instructions were actually present.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ComputeBudget {
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64,
+    pub heap_frame_bytes: Option<u32>,
+    pub loaded_accounts_data_size_limit: Option<u32>,
+    pub requested_compute_unit_limit: Option<u32>,
+    pub requested_compute_unit_price: Option<u64>,
+}
+
+impl ComputeBudget {
+    // Priority fee in lamports, rounded up like the runtime does
+    pub fn priority_fee(&self) -> u64 {
+        let micro_lamports = self.compute_unit_price as u128 * self.compute_unit_limit as u128;
+        let lamports = micro_lamports.div_ceil(MICRO_LAMPORTS_PER_LAMPORT as u128);
+        lamports.min(u64::MAX as u128) as u64
+    }
+}
+
+// Compute units a transaction may consume: its compute budget limit, or the
+// host field when the budget instructions don't decode (validation rejects
+// such transactions anyway)
+pub fn compute_units(tx: &Tx) -> u64 {
+    match parse_compute_budget(tx) {
+        Ok(budget) => budget.compute_unit_limit as u64,
+        Err(_) => tx.compute_limit as u64,
+    }
+}
+
+// Priority fee a transaction pays, with the same fallback as `compute_units`
+pub fn priority_fee(tx: &Tx) -> u64 {
+    match parse_compute_budget(tx) {
+        Ok(budget) => budget.priority_fee(),
+        Err(_) => tx.priority_fee,
+    }
+}
+
+pub fn is_compute_budget_instruction(tx: &Tx, instruction: &Instruction) -> bool {
+    // Programs can't come from lookup tables, so static keys suffice
+    tx.account_keys.get(instruction.program_id_index as usize) == Some(&COMPUTE_BUDGET_PROGRAM_ID)
+}
+
+// Decode a transaction's compute budget instructions with the runtime's
+// rules: each kind at most once, heap frames 32-256 KiB in 1 KiB steps, and
+// without an explicit limit 200k CU per non-budget instruction, capped at
+// 1.4M
+pub fn parse_compute_budget(tx: &Tx) -> Result<ComputeBudget, &'static str> {
+    let mut budget = ComputeBudget::default();
+    let mut other_instructions = 0u32;
+
+    for instruction in &tx.instructions {
+        if !is_compute_budget_instruction(tx, instruction) {
+            other_instructions += 1;
+            continue;
+        }
+
+        match ComputeBudgetInstruction::decode(&instruction.data)? {
+            ComputeBudgetInstruction::RequestHeapFrame(bytes) => {
+                if budget.heap_frame_bytes.replace(bytes).is_some() {
+                    return Err("Duplicate compute budget instruction");
+                }
+                if !(MIN_HEAP_FRAME_BYTES..=MAX_HEAP_FRAME_BYTES).contains(&bytes)
+                    || bytes % HEAP_FRAME_GRANULARITY != 0
+                {
+                    return Err("Invalid heap frame size");
+                }
+            }
+            ComputeBudgetInstruction::SetComputeUnitLimit(units) => {
+                if budget.requested_compute_unit_limit.replace(units).is_some() {
+                    return Err("Duplicate compute budget instruction");
+                }
+            }
+            ComputeBudgetInstruction::SetComputeUnitPrice(micro_lamports) => {
+                if budget.requested_compute_unit_price.replace(micro_lamports).is_some() {
+                    return Err("Duplicate compute budget instruction");
+                }
+            }
+            ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit(bytes) => {
+                if budget.loaded_accounts_data_size_limit.replace(bytes).is_some() {
+                    return Err("Duplicate compute budget instruction");
+                }
+                if bytes == 0 {
+                    return Err("Invalid loaded accounts data size limit");
+                }
+            }
+        }
+    }
+
+    let default_limit = other_instructions.saturating_mul(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT);
+    budget.compute_unit_limit = budget.requested_compute_unit_limit
+        .unwrap_or(default_limit)
+        .min(MAX_COMPUTE_UNIT_LIMIT);
+    budget.compute_unit_price = budget.requested_compute_unit_price.unwrap_or(0);
+
+    Ok(budget)
+}
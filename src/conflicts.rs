@@ -0,0 +1,174 @@
+use crate::lookup_tables;
+use crate::owned::{Bundle, Tx};
+use crate::types::*;
+use std::collections::HashSet;
+
+// Accounts a transaction takes locks on when it executes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountLocks {
+    pub writable: HashSet<Pubkey>,
+    pub readonly: HashSet<Pubkey>,
+}
+
+impl AccountLocks {
+    // Derive lock sets from the message header and the lookup table split.
+    // Fails when a lookup table the message uses isn't registered.
+    pub fn for_transaction(tx: &Tx) -> Result<Self, &'static str> {
+        let loaded = lookup_tables::resolve_account_keys(tx)?;
+
+        let mut locks = Self::default();
+        for (index, key) in loaded.keys.iter().enumerate() {
+            if loaded.is_writable(index, &tx.header) {
+                locks.writable.insert(*key);
+            } else {
+                locks.readonly.insert(*key);
+            }
+        }
+
+        // An account both written and read is locked for writing
+        let AccountLocks { writable, readonly } = &mut locks;
+        readonly.retain(|key| !writable.contains(key));
+
+        Ok(locks)
+    }
+
+    pub fn conflicts_with(&self, other: &Self) -> bool {
+        self.writable.iter().any(|key| other.writable.contains(key) || other.readonly.contains(key))
+            || other.writable.iter().any(|key| self.readonly.contains(key))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    WriteWrite,
+    ReadWrite,
+}
+
+// Two transactions contending for one account; `first` comes earlier in
+// the bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict {
+    pub first: usize,
+    pub second: usize,
+    pub account: Pubkey,
+    pub kind: ConflictKind,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConflictAnalysis {
+    // Per transaction, the earlier transactions it must run after
+    pub dependencies: Vec<Vec<usize>>,
+    pub write_write_conflicts: Vec<Conflict>,
+    pub read_write_conflicts: Vec<Conflict>,
+    // Transactions whose accounts couldn't be resolved; they depend on
+    // every earlier transaction
+    pub unresolved_transactions: Vec<usize>,
+    // Groups that can run in parallel, in execution order. No two
+    // transactions in a batch conflict.
+    pub parallel_batches: Vec<Vec<usize>>,
+}
+
+pub fn analyze_bundle(bundle: &Bundle) -> ConflictAnalysis {
+    let transactions = &bundle.transactions;
+    let count = transactions.len();
+
+    let locks: Vec<Option<AccountLocks>> = transactions.iter()
+        .map(|tx| AccountLocks::for_transaction(tx).ok())
+        .collect();
+
+    let mut analysis = ConflictAnalysis {
+        dependencies: vec![Vec::new(); count],
+        ..Default::default()
+    };
+
+    for (idx, lock) in locks.iter().enumerate() {
+        if lock.is_none() {
+            log::debug!("Transaction {} has unresolved accounts, treating as conflicting", idx);
+            analysis.unresolved_transactions.push(idx);
+        }
+    }
+
+    for later in 0..count {
+        for earlier in 0..later {
+            let (a, b) = match (&locks[earlier], &locks[later]) {
+                (Some(a), Some(b)) => (a, b),
+                _ => {
+                    analysis.dependencies[later].push(earlier);
+                    continue;
+                }
+            };
+
+            if record_conflicts(&mut analysis, earlier, later, a, b) {
+                analysis.dependencies[later].push(earlier);
+            }
+        }
+    }
+
+    analysis.parallel_batches = parallel_batches(&analysis.dependencies);
+
+    log::debug!(
+        "Conflict analysis: {} write-write, {} read-write, {} batches for {} txs",
+        analysis.write_write_conflicts.len(),
+        analysis.read_write_conflicts.len(),
+        analysis.parallel_batches.len(),
+        count
+    );
+
+    analysis
+}
+
+fn record_conflicts(
+    analysis: &mut ConflictAnalysis,
+    first: usize,
+    second: usize,
+    a: &AccountLocks,
+    b: &AccountLocks,
+) -> bool {
+    let mut write_write: Vec<Pubkey> = a.writable.intersection(&b.writable).copied().collect();
+    let mut read_write: Vec<Pubkey> = a.writable.intersection(&b.readonly)
+        .chain(a.readonly.intersection(&b.writable))
+        .copied()
+        .collect();
+
+    if write_write.is_empty() && read_write.is_empty() {
+        return false;
+    }
+
+    // Hash set order isn't stable; keep reports deterministic
+    write_write.sort_by_key(|key| key.bytes);
+    read_write.sort_by_key(|key| key.bytes);
+
+    analysis.write_write_conflicts.extend(write_write.into_iter().map(|account| Conflict {
+        first,
+        second,
+        account,
+        kind: ConflictKind::WriteWrite,
+    }));
+    analysis.read_write_conflicts.extend(read_write.into_iter().map(|account| Conflict {
+        first,
+        second,
+        account,
+        kind: ConflictKind::ReadWrite,
+    }));
+
+    true
+}
+
+// Each transaction lands one batch after the latest batch it depends on
+fn parallel_batches(dependencies: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut levels = vec![0usize; dependencies.len()];
+    let mut batches: Vec<Vec<usize>> = Vec::new();
+
+    // Dependencies always point to lower indices, so one pass suffices
+    for (idx, deps) in dependencies.iter().enumerate() {
+        let level = deps.iter().map(|&dep| levels[dep] + 1).max().unwrap_or(0);
+        levels[idx] = level;
+
+        if batches.len() <= level {
+            batches.resize_with(level + 1, Vec::new);
+        }
+        batches[level].push(idx);
+    }
+
+    batches
+}
@@ -0,0 +1,168 @@
+use crate::PLUGIN_STATE;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// EIP-1559-style base fee. Each slot's demand is compared with a target; the
+// per-transaction base fee for the next slot rises by up to 1/denominator
+// when the slot ran above target and decays by the same rule below it,
+// never dropping under the configured minimum fee.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CongestionConfig {
+    // When off, demand is still tracked but the base fee stays at the
+    // minimum fee
+    pub enabled: bool,
+    pub target_bundles_per_slot: u64,
+    pub target_compute_units_per_slot: u64,
+    pub max_change_denominator: u64,
+    pub max_base_fee_multiplier: u64,
+    pub history_slots: usize,
+}
+
+impl Default for CongestionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_bundles_per_slot: 16,
+            target_compute_units_per_slot: 12_000_000, // A quarter of a block
+            max_change_denominator: 8,
+            max_base_fee_multiplier: 100,
+            history_slots: 8,
+        }
+    }
+}
+
+impl CongestionConfig {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.target_bundles_per_slot == 0 || self.target_compute_units_per_slot == 0 {
+            return Err("Congestion targets must be positive");
+        }
+        if self.max_change_denominator == 0 {
+            return Err("Base fee change denominator must be positive");
+        }
+        if self.max_base_fee_multiplier == 0 {
+            return Err("Base fee multiplier cap must be positive");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotUsage {
+    pub slot: u64,
+    pub bundles: u64,
+    pub compute_units: u64,
+    pub base_fee: u64,
+}
+
+// Demand seen in the current slot plus the last few finished slots, oldest
+// first. Lives in `PluginState` so hosts and searchers can read it through
+// `get_plugin_state`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CongestionState {
+    pub slot: u64,
+    pub base_fee: u64,
+    pub slot_bundles: u64,
+    pub slot_compute_units: u64,
+    pub history: VecDeque<SlotUsage>,
+}
+
+// Skipped slots decay the fee one step each; past this many it has long
+// since reached the floor
+const MAX_DECAY_STEPS: u64 = 1024;
+
+impl CongestionState {
+    // Base fee charged for a bundle in `slot`, without recording anything
+    pub fn quote(&self, slot: u64, config: &CongestionConfig, min_fee: u64) -> u64 {
+        if !config.enabled {
+            return min_fee;
+        }
+
+        let mut projected = self.clone();
+        projected.advance_to(slot, config, min_fee);
+        projected.base_fee
+    }
+
+    // Count a processed bundle towards its slot's demand. Bundles for a slot
+    // that has already finished count towards the current one.
+    pub fn record(&mut self, slot: u64, compute_units: u64, config: &CongestionConfig, min_fee: u64) {
+        self.advance_to(slot, config, min_fee);
+        self.slot_bundles = self.slot_bundles.saturating_add(1);
+        self.slot_compute_units = self.slot_compute_units.saturating_add(compute_units);
+    }
+
+    fn advance_to(&mut self, slot: u64, config: &CongestionConfig, min_fee: u64) {
+        if !config.enabled {
+            self.base_fee = min_fee;
+        } else {
+            self.base_fee = self.base_fee.clamp(min_fee, max_base_fee(config, min_fee));
+        }
+
+        if slot <= self.slot {
+            return;
+        }
+
+        // First slot ever seen: nothing to close out
+        if self.slot == 0 && self.slot_bundles == 0 && self.history.is_empty() {
+            self.slot = slot;
+            return;
+        }
+
+        self.history.push_back(SlotUsage {
+            slot: self.slot,
+            bundles: self.slot_bundles,
+            compute_units: self.slot_compute_units,
+            base_fee: self.base_fee,
+        });
+        while self.history.len() > config.history_slots {
+            self.history.pop_front();
+        }
+
+        if config.enabled {
+            let utilization = (self.slot_bundles as f64 / config.target_bundles_per_slot as f64)
+                .max(self.slot_compute_units as f64 / config.target_compute_units_per_slot as f64);
+            self.base_fee = next_base_fee(self.base_fee, utilization, config, min_fee);
+
+            let empty_slots = (slot - self.slot - 1).min(MAX_DECAY_STEPS);
+            for _ in 0..empty_slots {
+                self.base_fee = next_base_fee(self.base_fee, 0.0, config, min_fee);
+            }
+        }
+
+        self.slot = slot;
+        self.slot_bundles = 0;
+        self.slot_compute_units = 0;
+    }
+}
+
+fn max_base_fee(config: &CongestionConfig, min_fee: u64) -> u64 {
+    min_fee.saturating_mul(config.max_base_fee_multiplier).max(min_fee)
+}
+
+// utilization is demand / target; 1.0 keeps the fee where it is
+fn next_base_fee(base_fee: u64, utilization: f64, config: &CongestionConfig, min_fee: u64) -> u64 {
+    let delta = (utilization - 1.0) / config.max_change_denominator as f64;
+    // Move at least one lamport so small fees don't get stuck
+    let mut next = (base_fee as f64 * (1.0 + delta)).round() as u64;
+    if utilization > 1.0 && next == base_fee {
+        next = base_fee.saturating_add(1);
+    }
+    next.clamp(min_fee, max_base_fee(config, min_fee))
+}
+
+// Base fee for a bundle in `slot` under the current plugin config
+pub fn current_base_fee(slot: u64) -> u64 {
+    match PLUGIN_STATE.lock() {
+        Ok(state) => state.congestion.quote(slot, &state.config.congestion, state.config.min_fee_lamports),
+        Err(_) => 5000, // Fallback to the default minimum fee
+    }
+}
+
+pub fn record_bundle(slot: u64, compute_units: u64) {
+    if let Ok(mut state) = PLUGIN_STATE.lock() {
+        let config = state.config.congestion.clone();
+        let min_fee = state.config.min_fee_lamports;
+        state.congestion.record(slot, compute_units, &config, min_fee);
+    }
+}
@@ -0,0 +1,415 @@
+use crate::compute_budget;
+use crate::owned::Bundle;
+use serde::{Deserialize, Serialize};
+
+// Everything a fee policy can charge on. Pipelines fill in the oracle and
+// arbitrage counts when they have them; the MEV estimate comes from
+// `mev::estimate_bundle_mev`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeContext {
+    // Per-transaction congestion base fee for the bundle's slot
+    pub base_fee: u64,
+    pub transactions: u64,
+    pub compute_units: u64,
+    pub priority_fees: u64,
+    pub tip: u64,
+    pub oracle_injections: u64,
+    pub arbitrage_opportunities: u64,
+    // Lamports of sandwich and back-run value in the bundle's swaps
+    pub estimated_mev: u64,
+}
+
+impl FeeContext {
+    // Compute units and priority fees come from the compute budget
+    // instructions, falling back to the host fields only when those don't
+    // decode (validation rejects such bundles anyway)
+    pub fn from_bundle(bundle: &Bundle) -> Self {
+        let mut context = Self {
+            transactions: bundle.transactions.len() as u64,
+            tip: bundle.metadata.tip_amount,
+            ..Default::default()
+        };
+
+        for tx in &bundle.transactions {
+            let (compute_units, priority_fee) = match compute_budget::parse_compute_budget(tx) {
+                Ok(budget) => (budget.compute_unit_limit as u64, budget.priority_fee()),
+                Err(_) => (tx.compute_limit as u64, tx.priority_fee),
+            };
+            context.compute_units = context.compute_units.saturating_add(compute_units);
+            context.priority_fees = context.priority_fees.saturating_add(priority_fee);
+        }
+
+        context
+    }
+
+    pub fn with_base_fee(mut self, base_fee: u64) -> Self {
+        self.base_fee = base_fee;
+        self
+    }
+
+    pub fn with_oracle_injections(mut self, count: usize) -> Self {
+        self.oracle_injections = count as u64;
+        self
+    }
+
+    pub fn with_arbitrage_opportunities(mut self, count: usize) -> Self {
+        self.arbitrage_opportunities = count as u64;
+        self
+    }
+
+    pub fn with_estimated_mev(mut self, lamports: u64) -> Self {
+        self.estimated_mev = lamports;
+        self
+    }
+
+    pub fn metric(&self, metric: FeeMetric) -> u64 {
+        match metric {
+            FeeMetric::BaseFee => self.base_fee,
+            FeeMetric::Transactions => self.transactions,
+            FeeMetric::ComputeUnits => self.compute_units,
+            FeeMetric::PriorityFees => self.priority_fees,
+            FeeMetric::Tip => self.tip,
+            FeeMetric::OracleInjections => self.oracle_injections,
+            FeeMetric::ArbitrageOpportunities => self.arbitrage_opportunities,
+            FeeMetric::EstimatedMev => self.estimated_mev,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeMetric {
+    BaseFee,
+    Transactions,
+    ComputeUnits,
+    PriorityFees,
+    Tip,
+    OracleInjections,
+    ArbitrageOpportunities,
+    EstimatedMev,
+}
+
+pub trait FeePolicy: Send + Sync {
+    fn fee(&self, context: &FeeContext) -> u64;
+}
+
+pub struct FlatFee {
+    pub lamports: u64,
+}
+
+impl FeePolicy for FlatFee {
+    fn fee(&self, _context: &FeeContext) -> u64 {
+        self.lamports
+    }
+}
+
+// Charges `lamports` for every unit of `metric` above `free_units`
+pub struct PerUnitFee {
+    pub metric: FeeMetric,
+    pub lamports: u64,
+    pub free_units: u64,
+}
+
+impl FeePolicy for PerUnitFee {
+    fn fee(&self, context: &FeeContext) -> u64 {
+        context.metric(self.metric)
+            .saturating_sub(self.free_units)
+            .saturating_mul(self.lamports)
+    }
+}
+
+// The congestion base fee for every transaction in the bundle
+pub struct BaseFee;
+
+impl FeePolicy for BaseFee {
+    fn fee(&self, context: &FeeContext) -> u64 {
+        context.base_fee.saturating_mul(context.transactions)
+    }
+}
+
+pub struct PerComputeUnitFee {
+    pub compute_units_per_lamport: u64,
+}
+
+impl FeePolicy for PerComputeUnitFee {
+    fn fee(&self, context: &FeeContext) -> u64 {
+        context.compute_units / self.compute_units_per_lamport.max(1)
+    }
+}
+
+// A fraction (0.001 = 0.1%) of the tip, the priority fees or the MEV
+pub struct PercentageFee {
+    pub metric: FeeMetric,
+    pub rate: f64,
+}
+
+impl FeePolicy for PercentageFee {
+    fn fee(&self, context: &FeeContext) -> u64 {
+        // Float to int casts saturate
+        (context.metric(self.metric) as f64 * self.rate) as u64
+    }
+}
+
+// Charges the highest tier whose threshold the metric reaches
+pub struct TieredFee {
+    pub metric: FeeMetric,
+    pub tiers: Vec<FeeTier>,
+}
+
+impl FeePolicy for TieredFee {
+    fn fee(&self, context: &FeeContext) -> u64 {
+        let value = context.metric(self.metric);
+        self.tiers.iter()
+            .filter(|tier| value >= tier.threshold)
+            .max_by_key(|tier| tier.threshold)
+            .map(|tier| tier.lamports)
+            .unwrap_or(0)
+    }
+}
+
+// Scales another policy once the metric reaches a threshold
+pub struct SurgeFee {
+    pub policy: Box<dyn FeePolicy>,
+    pub metric: FeeMetric,
+    pub threshold: u64,
+    pub multiplier: f64,
+}
+
+impl FeePolicy for SurgeFee {
+    fn fee(&self, context: &FeeContext) -> u64 {
+        let fee = self.policy.fee(context);
+        if context.metric(self.metric) >= self.threshold {
+            (fee as f64 * self.multiplier) as u64
+        } else {
+            fee
+        }
+    }
+}
+
+pub struct MaxFee {
+    pub policies: Vec<Box<dyn FeePolicy>>,
+}
+
+impl FeePolicy for MaxFee {
+    fn fee(&self, context: &FeeContext) -> u64 {
+        self.policies.iter().map(|policy| policy.fee(context)).max().unwrap_or(0)
+    }
+}
+
+pub struct SumFee {
+    pub policies: Vec<Box<dyn FeePolicy>>,
+}
+
+impl FeePolicy for SumFee {
+    fn fee(&self, context: &FeeContext) -> u64 {
+        self.policies.iter().fold(0u64, |total, policy| total.saturating_add(policy.fee(context)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeTier {
+    pub threshold: u64,
+    pub lamports: u64,
+}
+
+// JSON form of a fee policy, e.g.
+// {"type": "max", "policies": [{"type": "per_transaction", "lamports": 5000},
+//                              {"type": "per_compute_unit", "compute_units_per_lamport": 1000}]}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeePolicyConfig {
+    BaseFee,
+    Flat {
+        lamports: u64,
+    },
+    PerTransaction {
+        lamports: u64,
+        #[serde(default)]
+        free_transactions: u64,
+    },
+    PerComputeUnit {
+        compute_units_per_lamport: u64,
+    },
+    PercentageOfTip {
+        rate: f64,
+    },
+    PercentageOfPriorityFees {
+        rate: f64,
+    },
+    PercentageOfMev {
+        rate: f64,
+    },
+    PerOracleInjection {
+        lamports: u64,
+        #[serde(default)]
+        free_injections: u64,
+    },
+    PerArbitrage {
+        lamports: u64,
+    },
+    Tiered {
+        metric: FeeMetric,
+        tiers: Vec<FeeTier>,
+    },
+    Surge {
+        policy: Box<FeePolicyConfig>,
+        metric: FeeMetric,
+        threshold: u64,
+        multiplier: f64,
+    },
+    Max {
+        policies: Vec<FeePolicyConfig>,
+    },
+    Sum {
+        policies: Vec<FeePolicyConfig>,
+    },
+}
+
+impl FeePolicyConfig {
+    pub fn build(&self) -> Result<Box<dyn FeePolicy>, &'static str> {
+        let policy: Box<dyn FeePolicy> = match self {
+            Self::BaseFee => Box::new(BaseFee),
+            Self::Flat { lamports } => Box::new(FlatFee { lamports: *lamports }),
+            Self::PerTransaction { lamports, free_transactions } => Box::new(PerUnitFee {
+                metric: FeeMetric::Transactions,
+                lamports: *lamports,
+                free_units: *free_transactions,
+            }),
+            Self::PerComputeUnit { compute_units_per_lamport } => {
+                if *compute_units_per_lamport == 0 {
+                    return Err("Compute units per lamport must be positive");
+                }
+                Box::new(PerComputeUnitFee { compute_units_per_lamport: *compute_units_per_lamport })
+            }
+            Self::PercentageOfTip { rate } => Box::new(PercentageFee {
+                metric: FeeMetric::Tip,
+                rate: validate_rate(*rate)?,
+            }),
+            Self::PercentageOfPriorityFees { rate } => Box::new(PercentageFee {
+                metric: FeeMetric::PriorityFees,
+                rate: validate_rate(*rate)?,
+            }),
+            Self::PercentageOfMev { rate } => Box::new(PercentageFee {
+                metric: FeeMetric::EstimatedMev,
+                rate: validate_rate(*rate)?,
+            }),
+            Self::PerOracleInjection { lamports, free_injections } => Box::new(PerUnitFee {
+                metric: FeeMetric::OracleInjections,
+                lamports: *lamports,
+                free_units: *free_injections,
+            }),
+            Self::PerArbitrage { lamports } => Box::new(PerUnitFee {
+                metric: FeeMetric::ArbitrageOpportunities,
+                lamports: *lamports,
+                free_units: 0,
+            }),
+            Self::Tiered { metric, tiers } => {
+                if tiers.is_empty() {
+                    return Err("Tiered fee needs at least one tier");
+                }
+                Box::new(TieredFee { metric: *metric, tiers: tiers.clone() })
+            }
+            Self::Surge { policy, metric, threshold, multiplier } => {
+                if !multiplier.is_finite() || *multiplier < 1.0 {
+                    return Err("Surge multiplier must be at least 1");
+                }
+                Box::new(SurgeFee {
+                    policy: policy.build()?,
+                    metric: *metric,
+                    threshold: *threshold,
+                    multiplier: *multiplier,
+                })
+            }
+            Self::Max { policies } => Box::new(MaxFee { policies: build_all(policies)? }),
+            Self::Sum { policies } => Box::new(SumFee { policies: build_all(policies)? }),
+        };
+        Ok(policy)
+    }
+}
+
+fn validate_rate(rate: f64) -> Result<f64, &'static str> {
+    if !rate.is_finite() || rate < 0.0 {
+        return Err("Fee rate must be a non-negative number");
+    }
+    Ok(rate)
+}
+
+fn build_all(policies: &[FeePolicyConfig]) -> Result<Vec<Box<dyn FeePolicy>>, &'static str> {
+    if policies.is_empty() {
+        return Err("Combined fee policy needs at least one policy");
+    }
+    policies.iter().map(FeePolicyConfig::build).collect()
+}
+
+// Fee policies per pipeline stage, read from `PluginConfig.fee_schedule`.
+// Stages left out use the built-in schedule.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<FeePolicyConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oracle: Option<FeePolicyConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub institutional: Option<FeePolicyConfig>,
+}
+
+impl FeeSchedule {
+    // Largest of the per-transaction base fee (the minimum fee unless
+    // congestion pricing raised it), a share of the priority fees, the same
+    // share of the estimated MEV and 1 lamport per 1000 CU
+    pub fn default_bundle_policy(fee_percentage: f32) -> FeePolicyConfig {
+        FeePolicyConfig::Max {
+            policies: vec![
+                FeePolicyConfig::BaseFee,
+                FeePolicyConfig::PercentageOfPriorityFees { rate: fee_percentage as f64 },
+                FeePolicyConfig::PercentageOfMev { rate: fee_percentage as f64 },
+                FeePolicyConfig::PerComputeUnit { compute_units_per_lamport: 1000 },
+            ],
+        }
+    }
+
+    // 0.01 SOL per price injection, plus 2000 lamports for each beyond five
+    pub fn default_oracle_policy() -> FeePolicyConfig {
+        FeePolicyConfig::Sum {
+            policies: vec![
+                FeePolicyConfig::PerOracleInjection { lamports: 10_000, free_injections: 0 },
+                FeePolicyConfig::PerOracleInjection { lamports: 2_000, free_injections: 5 },
+            ],
+        }
+    }
+
+    // 0.015 SOL base, 0.005 SOL per arbitrage opportunity and 1000 lamports
+    // per transaction beyond ten
+    pub fn default_institutional_policy() -> FeePolicyConfig {
+        FeePolicyConfig::Sum {
+            policies: vec![
+                FeePolicyConfig::Flat { lamports: 15_000 },
+                FeePolicyConfig::PerArbitrage { lamports: 5_000 },
+                FeePolicyConfig::PerTransaction { lamports: 1_000, free_transactions: 10 },
+            ],
+        }
+    }
+}
+
+// The built policies every pipeline computes its fees through
+pub struct FeeEngine {
+    pub bundle: Box<dyn FeePolicy>,
+    pub oracle: Box<dyn FeePolicy>,
+    pub institutional: Box<dyn FeePolicy>,
+}
+
+impl FeeEngine {
+    pub fn new(schedule: &FeeSchedule, fee_percentage: f32) -> Result<Self, &'static str> {
+        let bundle = schedule.bundle.clone()
+            .unwrap_or_else(|| FeeSchedule::default_bundle_policy(fee_percentage));
+        let oracle = schedule.oracle.clone().unwrap_or_else(FeeSchedule::default_oracle_policy);
+        let institutional = schedule.institutional.clone()
+            .unwrap_or_else(FeeSchedule::default_institutional_policy);
+
+        Ok(Self {
+            bundle: bundle.build()?,
+            oracle: oracle.build()?,
+            institutional: institutional.build()?,
+        })
+    }
+}
@@ -1,100 +1,111 @@
-use crate::types::*;
+use crate::congestion;
+use crate::fee_policy::{FeeContext, FeeEngine, FeeSchedule};
+use crate::mev;
+use crate::owned::Bundle;
+use crate::tips;
 use crate::PLUGIN_STATE;
+use serde::{Deserialize, Serialize};
+
+// How the required plugin fee was reached. Each pipeline stage charges
+// through its policy in the fee engine; V2 adds the oracle fee on top of
+// the bundle fee, and V3 requires at least the institutional fee.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeeBreakdown {
+    pub bundle_fee: u64,
+    pub oracle_fee: u64,
+    pub institutional_fee: u64,
+    pub required_fee: u64,
+    pub paid_fee: u64,
+}
 
-pub unsafe fn calculate_bundle_fee(bundle: *const TransactionBundle) -> u64 {
-    if bundle.is_null() {
-        return 0;
-    }
-
-    let bundle_ref = match bundle.as_ref() {
-        Some(b) => b,
-        None => return 0,
-    };
-
-    // Get configuration
-    let (min_fee, fee_percentage) = match PLUGIN_STATE.lock() {
-        Ok(state) => (state.config.min_fee_lamports, state.config.fee_percentage),
-        Err(_) => (5000, 0.001), // Fallback to defaults
+// Fee engine for the current config. A schedule that doesn't build can only
+// arrive through `set_plugin_state`; it falls back to the built-in one.
+pub fn fee_engine() -> FeeEngine {
+    let (schedule, fee_percentage) = match PLUGIN_STATE.lock() {
+        Ok(state) => (state.config.fee_schedule.clone(), state.config.fee_percentage),
+        Err(_) => (FeeSchedule::default(), 0.001), // Fallback to defaults
     };
 
-    // Calculate base fee from transaction count
-    let base_fee = min_fee * bundle_ref.transaction_count as u64;
-
-    // Calculate percentage-based fee from total priority fees
-    let total_priority_fees = calculate_total_priority_fees(bundle_ref);
-    let percentage_fee = (total_priority_fees as f64 * fee_percentage as f64) as u64;
-
-    // Calculate compute-based fee
-    let compute_fee = calculate_compute_fee(bundle_ref);
-
-    // Return the maximum of all fee calculations
-    base_fee.max(percentage_fee).max(compute_fee)
+    FeeEngine::new(&schedule, fee_percentage).unwrap_or_else(|e| {
+        log::error!("Invalid fee schedule, using built-in fees: {}", e);
+        FeeEngine::new(&FeeSchedule::default(), fee_percentage)
+            .expect("built-in fee schedule is valid")
+    })
 }
 
-unsafe fn calculate_total_priority_fees(bundle: &TransactionBundle) -> u64 {
-    if bundle.transactions.is_null() {
-        return 0;
-    }
-
-    let transactions = std::slice::from_raw_parts(
-        bundle.transactions,
-        bundle.transaction_count as usize
-    );
+// What the fee policies see for a bundle, priced at its slot's base fee
+pub fn fee_context(bundle: &Bundle) -> FeeContext {
+    FeeContext::from_bundle(bundle)
+        .with_base_fee(congestion::current_base_fee(bundle.metadata.slot))
+        .with_estimated_mev(mev::estimate_bundle_mev(bundle).total)
+}
 
-    transactions.iter()
-        .map(|tx| tx.priority_fee)
-        .sum()
+pub fn calculate_bundle_fee(bundle: &Bundle) -> u64 {
+    calculate_fee_breakdown(bundle).required_fee
 }
 
-unsafe fn calculate_compute_fee(bundle: &TransactionBundle) -> u64 {
-    if bundle.transactions.is_null() {
-        return 0;
-    }
+pub fn calculate_fee_breakdown(bundle: &Bundle) -> FeeBreakdown {
+    let bundle_fee = fee_engine().bundle.fee(&fee_context(bundle));
 
-    let transactions = std::slice::from_raw_parts(
-        bundle.transactions,
-        bundle.transaction_count as usize
-    );
+    FeeBreakdown {
+        bundle_fee,
+        required_fee: bundle_fee,
+        paid_fee: bundle.metadata.plugin_fees,
+        ..Default::default()
+    }
+}
 
-    let total_compute: u64 = transactions.iter()
-        .map(|tx| tx.compute_limit as u64)
-        .sum();
+pub fn calculate_oracle_fee(bundle: &Bundle, injection_count: usize) -> u64 {
+    let context = fee_context(bundle).with_oracle_injections(injection_count);
+    fee_engine().oracle.fee(&context)
+}
 
-    // 1 lamport per 1000 compute units
-    total_compute / 1000
+pub fn calculate_institutional_fee(bundle: &Bundle, arbitrage_count: usize) -> u64 {
+    let context = fee_context(bundle).with_arbitrage_opportunities(arbitrage_count);
+    fee_engine().institutional.fee(&context)
 }
 
-pub fn estimate_bundle_value(bundle: &TransactionBundle) -> BundleValue {
-    let mut value = BundleValue {
-        total_priority_fees: 0,
-        total_tips: 0,
-        estimated_mev: 0,
-        plugin_fee: 0,
+pub fn estimate_bundle_value(bundle: &Bundle) -> BundleValue {
+    let total_priority_fees = FeeContext::from_bundle(bundle).priority_fees;
+
+    // With tip accounts configured, count what the bundle actually transfers
+    // rather than what the host declared
+    let tip_accounts = tips::configured_tip_accounts();
+    let transaction_tips = tips::tips_per_transaction(bundle, &tip_accounts);
+    let total_tips = if tip_accounts.is_empty() {
+        bundle.metadata.tip_amount
+    } else {
+        transaction_tips.iter().fold(0u64, |total, &tip| total.saturating_add(tip))
     };
 
-    unsafe {
-        value.total_priority_fees = calculate_total_priority_fees(bundle);
-        value.total_tips = bundle.metadata.tip_amount;
-        value.plugin_fee = calculate_bundle_fee(bundle);
-        
-        // MEV estimation would require more complex analysis
-        // For now, use a simple heuristic based on priority fees
-        value.estimated_mev = value.total_priority_fees / 10;
-    }
+    let mev = mev::estimate_bundle_mev(bundle);
 
-    value
+    BundleValue {
+        total_priority_fees,
+        total_tips,
+        transaction_tips,
+        estimated_mev: mev.total,
+        transaction_mev: mev.transaction_mev,
+        plugin_fee: calculate_bundle_fee(bundle),
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct BundleValue {
     pub total_priority_fees: u64,
     pub total_tips: u64,
+    // Lamports each transaction sends to the tip accounts
+    pub transaction_tips: Vec<u64>,
     pub estimated_mev: u64,
+    // Sandwich and back-run value of each transaction's swaps
+    pub transaction_mev: Vec<u64>,
     pub plugin_fee: u64,
 }
 
 impl BundleValue {
     pub fn total(&self) -> u64 {
-        self.total_priority_fees + self.total_tips + self.estimated_mev
+        self.total_priority_fees
+            .saturating_add(self.total_tips)
+            .saturating_add(self.estimated_mev)
     }
 }
\ No newline at end of file
@@ -1,3 +1,6 @@
+use crate::compute_budget;
+use crate::owned::{Bundle, Tx};
+use crate::report::{ProcessingReport, Rejection, Stage};
 use crate::types::*;
 use crate::PLUGIN_STATE;
 
@@ -6,6 +9,7 @@ pub struct InstitutionalSequencer {
     pub market_maker_priority: bool,
     pub cross_chain_enabled: bool,
     pub compliance_enabled: bool,
+    pub risk_limits: RiskParameters,
 }
 
 #[cfg(feature = "institutional")]
@@ -15,144 +19,116 @@ impl InstitutionalSequencer {
             market_maker_priority: true,
             cross_chain_enabled: config.cross_chain_enabled,
             compliance_enabled: config.compliance_requirements.kyc_required,
+            risk_limits: config.risk_limits.clone(),
         }
     }
 
-    pub unsafe fn sequence_institutional_bundle(
+    pub fn sequence_institutional_bundle(
         &self,
-        bundle: &TransactionBundle,
+        bundle: &Bundle,
+        report: &mut ProcessingReport,
     ) -> i32 {
         // 1. Apply market maker priority
         if self.market_maker_priority {
-            if let Err(err) = self.apply_market_maker_priority(bundle) {
-                return err;
+            report.enter(Stage::MarketMakerPriority);
+            match self.apply_market_maker_priority(bundle) {
+                Ok(market_maker_txs) => report.market_maker_transactions = market_maker_txs,
+                Err(rejection) => return report.record(rejection),
             }
         }
 
         // 2. Check compliance if enabled
         if self.compliance_enabled {
-            if let Err(err) = self.validate_compliance(bundle) {
-                return err;
+            report.enter(Stage::Compliance);
+            if let Err(rejection) = self.validate_compliance(bundle) {
+                return report.record(rejection);
             }
         }
 
         // 3. Apply risk management
-        if let Err(err) = self.apply_risk_limits(bundle) {
-            return err;
+        report.enter(Stage::RiskLimits);
+        if let Err(rejection) = self.apply_risk_limits(bundle) {
+            return report.record(rejection);
         }
 
         SUCCESS
     }
 
-    pub unsafe fn apply_market_maker_priority(&self, bundle: &TransactionBundle) -> Result<(), i32> {
+    // Returns the indices of the detected market maker transactions
+    pub fn apply_market_maker_priority(&self, bundle: &Bundle) -> Result<Vec<usize>, Rejection> {
         // Prioritize market maker transactions
         // For weekend project: simple priority boost
-        log::debug!("Applying market maker priority to {} transactions", bundle.transaction_count);
-        
-        if bundle.transactions.is_null() || bundle.transaction_count == 0 {
-            return Ok(());
-        }
-
-        let transactions = std::slice::from_raw_parts(
-            bundle.transactions,
-            bundle.transaction_count as usize,
-        );
+        log::debug!("Applying market maker priority to {} transactions", bundle.transactions.len());
 
         // Count market maker transactions (simplified detection)
-        let mut mm_count = 0;
-        for (idx, transaction) in transactions.iter().enumerate() {
+        let mut market_maker_txs = Vec::new();
+        for (idx, transaction) in bundle.transactions.iter().enumerate() {
             if self.is_market_maker_transaction(transaction) {
-                mm_count += 1;
+                market_maker_txs.push(idx);
                 log::debug!("Market maker transaction detected at index {}", idx);
             }
         }
 
-        log::debug!("Found {} market maker transactions for priority processing", mm_count);
-        Ok(())
+        log::debug!("Found {} market maker transactions for priority processing", market_maker_txs.len());
+        Ok(market_maker_txs)
     }
 
-    pub unsafe fn validate_compliance(&self, bundle: &TransactionBundle) -> Result<(), i32> {
+    pub fn validate_compliance(&self, bundle: &Bundle) -> Result<(), Rejection> {
         // Basic compliance checks
         log::debug!("Validating compliance for institutional bundle");
         
-        if bundle.transactions.is_null() || bundle.transaction_count == 0 {
+        if bundle.transactions.is_empty() {
             return Ok(());
         }
 
         // Check transaction count limits (compliance constraint)
-        if bundle.transaction_count > 50 {
-            log::error!("Bundle exceeds institutional transaction limit: {} > 50", bundle.transaction_count);
-            return Err(ERROR_INSTITUTIONAL_COMPLIANCE);
-        }
-
-        // Check fee requirements for institutional processing
-        if bundle.metadata.plugin_fees < 20000 { // Higher institutional minimum
-            log::error!("Insufficient fee for institutional processing: {} < 20000", bundle.metadata.plugin_fees);
-            return Err(ERROR_INSUFFICIENT_FEE);
+        if bundle.transactions.len() > 50 {
+            let reason = format!("Bundle exceeds institutional transaction limit: {} > 50", bundle.transactions.len());
+            log::error!("{}", reason);
+            return Err(Rejection::new(Stage::Compliance, ERROR_INSTITUTIONAL_COMPLIANCE, None, reason));
         }
 
         Ok(())
     }
 
-    pub unsafe fn apply_risk_limits(&self, bundle: &TransactionBundle) -> Result<(), i32> {
+    pub fn apply_risk_limits(&self, bundle: &Bundle) -> Result<(), Rejection> {
         // Simple risk limit checks
         log::debug!("Applying risk limits to institutional bundle");
-        
-        if bundle.transactions.is_null() || bundle.transaction_count == 0 {
-            return Ok(());
-        }
-
-        let transactions = std::slice::from_raw_parts(
-            bundle.transactions,
-            bundle.transaction_count as usize,
-        );
 
         // Calculate estimated volume for risk assessment
         let mut total_estimated_value = 0u64;
-        for transaction in transactions {
+        for transaction in &bundle.transactions {
             // Simplified value estimation based on priority fee
-            total_estimated_value += transaction.priority_fee * 1000; // Rough SOL value estimate
+            let estimate = compute_budget::priority_fee(transaction).saturating_mul(1000); // Rough SOL value estimate
+            total_estimated_value = total_estimated_value.saturating_add(estimate);
         }
 
-        // Check against risk limits (simplified)
-        const MAX_BUNDLE_VALUE: u64 = 1_000_000_000_000; // 1M SOL equivalent
-        if total_estimated_value > MAX_BUNDLE_VALUE {
-            log::error!("Bundle value exceeds risk limit: {} > {}", total_estimated_value, MAX_BUNDLE_VALUE);
-            return Err(ERROR_INSTITUTIONAL_RISK_LIMIT);
+        // Check against the configured position limit (simplified)
+        let max_bundle_value = self.risk_limits.max_position_size;
+        if total_estimated_value > max_bundle_value {
+            let reason = format!("Bundle value exceeds risk limit: {} > {}", total_estimated_value, max_bundle_value);
+            log::error!("{}", reason);
+            return Err(Rejection::new(Stage::RiskLimits, ERROR_INSTITUTIONAL_RISK_LIMIT, None, reason));
         }
 
         log::debug!("Risk check passed: bundle value {} within limits", total_estimated_value);
         Ok(())
     }
 
-    pub unsafe fn is_market_maker_transaction(&self, transaction: &Transaction) -> bool {
+    pub fn is_market_maker_transaction(&self, transaction: &Tx) -> bool {
         // Simplified market maker detection
         // In reality, this would check program IDs, instruction patterns, etc.
         
-        if transaction.message.instructions.is_null() {
-            return false;
-        }
-
-        let instructions = std::slice::from_raw_parts(
-            transaction.message.instructions,
-            transaction.message.instructions_count as usize,
-        );
-
         // Check for market making patterns (simplified)
-        for instruction in instructions {
-            // Look for patterns that suggest market making activity
-            if instruction.data_len > 0 && !instruction.data.is_null() {
-                let data = std::slice::from_raw_parts(instruction.data, instruction.data_len as usize);
-                
-                // Simple heuristic: market making often involves specific instruction patterns
-                if data.len() >= 8 {
-                    let instruction_discriminator = &data[..8];
-                    // Check for common AMM/market making instruction discriminators
-                    match instruction_discriminator {
-                        [0x66, 0x06, 0x3d, 0x12, 0x01, 0x6f, 0x8e, 0xa5] => return true, // swap
-                        [0xf8, 0xc6, 0x9e, 0x91, 0xe1, 0x7a, 0x9c, 0x93] => return true, // provide liquidity
-                        _ => continue,
-                    }
+        for instruction in &transaction.instructions {
+            // Simple heuristic: market making often involves specific instruction patterns
+            if instruction.data.len() >= 8 {
+                let instruction_discriminator = &instruction.data[..8];
+                // Check for common AMM/market making instruction discriminators
+                match instruction_discriminator {
+                    [0x66, 0x06, 0x3d, 0x12, 0x01, 0x6f, 0x8e, 0xa5] => return true, // swap
+                    [0xf8, 0xc6, 0x9e, 0x91, 0xe1, 0x7a, 0x9c, 0x93] => return true, // provide liquidity
+                    _ => continue,
                 }
             }
         }
@@ -166,15 +142,22 @@ pub struct CrossChainDetector {
     pub enabled: bool,
 }
 
+#[cfg(feature = "institutional")]
+impl Default for CrossChainDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(feature = "institutional")]
 impl CrossChainDetector {
     pub fn new() -> Self {
         Self { enabled: true }
     }
 
-    pub unsafe fn detect_arbitrage_opportunities(
+    pub fn detect_arbitrage_opportunities(
         &self,
-        bundle: &TransactionBundle,
+        bundle: &Bundle,
     ) -> Vec<ArbitrageOpportunity> {
         let mut opportunities = Vec::new();
         
@@ -185,17 +168,8 @@ impl CrossChainDetector {
         // Simple arbitrage detection for demo
         log::debug!("Scanning for cross-chain arbitrage opportunities");
         
-        if bundle.transactions.is_null() || bundle.transaction_count == 0 {
-            return opportunities;
-        }
-
-        let transactions = std::slice::from_raw_parts(
-            bundle.transactions,
-            bundle.transaction_count as usize,
-        );
-
         // Look for patterns that suggest arbitrage potential
-        for (idx, transaction) in transactions.iter().enumerate() {
+        for (idx, transaction) in bundle.transactions.iter().enumerate() {
             if self.has_arbitrage_potential(transaction) {
                 log::debug!("Potential arbitrage opportunity detected in transaction {}", idx);
                 
@@ -216,34 +190,23 @@ impl CrossChainDetector {
         opportunities
     }
 
-    pub unsafe fn has_arbitrage_potential(&self, transaction: &Transaction) -> bool {
+    pub fn has_arbitrage_potential(&self, transaction: &Tx) -> bool {
         // Simplified arbitrage detection
         // Look for transactions that might be part of arbitrage strategies
         
-        if transaction.message.instructions.is_null() {
-            return false;
-        }
-
-        let instructions = std::slice::from_raw_parts(
-            transaction.message.instructions,
-            transaction.message.instructions_count as usize,
-        );
+        let instructions = &transaction.instructions;
 
         // Arbitrage often involves multiple swaps or complex instruction patterns
         if instructions.len() >= 2 {
             // Check for patterns suggesting arbitrage
             let has_swap_pattern = instructions.iter().any(|inst| {
-                if inst.data_len >= 8 && !inst.data.is_null() {
-                    let data = std::slice::from_raw_parts(inst.data, 8);
-                    // Look for swap discriminators
-                    matches!(data, [0x66, 0x06, 0x3d, 0x12, 0x01, 0x6f, 0x8e, 0xa5])
-                } else {
-                    false
-                }
+                // Look for swap discriminators
+                inst.data.len() >= 8
+                    && matches!(inst.data[..8], [0x66, 0x06, 0x3d, 0x12, 0x01, 0x6f, 0x8e, 0xa5])
             });
 
             // High priority fee often indicates arbitrage urgency
-            let has_high_priority = transaction.priority_fee > 100000; // 0.1 SOL
+            let has_high_priority = compute_budget::priority_fee(transaction) > 100000; // 0.1 SOL
 
             return has_swap_pattern && has_high_priority;
         }
@@ -253,16 +216,11 @@ impl CrossChainDetector {
 }
 
 #[cfg(feature = "institutional")]
-pub unsafe fn process_institutional_bundle(bundle: *mut TransactionBundle) -> i32 {
-    let bundle_ref = match bundle.as_ref() {
-        Some(b) => b,
-        None => return ERROR_NULL_POINTER,
-    };
-
+pub fn process_institutional_bundle(bundle: &Bundle, report: &mut ProcessingReport) -> i32 {
     // First run V2 oracle processing if available
     #[cfg(feature = "oracle")]
     {
-        let oracle_result = crate::oracle_processing::process_oracle_bundle(bundle);
+        let oracle_result = crate::oracle_processing::process_oracle_bundle(bundle, report);
         if oracle_result != SUCCESS {
             log::error!("Oracle processing failed in V3 pipeline: {}", oracle_result);
             return oracle_result;
@@ -272,7 +230,7 @@ pub unsafe fn process_institutional_bundle(bundle: *mut TransactionBundle) -> i3
     #[cfg(not(feature = "oracle"))]
     {
         // Fall back to V1 processing if oracle not available
-        let v1_result = crate::processing::process_bundle(bundle);
+        let v1_result = crate::processing::process_bundle(bundle, report);
         if v1_result != SUCCESS {
             log::error!("V1 processing failed in V3 pipeline: {}", v1_result);
             return v1_result;
@@ -280,8 +238,8 @@ pub unsafe fn process_institutional_bundle(bundle: *mut TransactionBundle) -> i3
     }
 
     // Then apply V3 institutional features
-    let sequencer = InstitutionalSequencer::new(&get_default_institutional_config());
-    let institutional_result = sequencer.sequence_institutional_bundle(bundle_ref);
+    let sequencer = InstitutionalSequencer::new(&get_institutional_config());
+    let institutional_result = sequencer.sequence_institutional_bundle(bundle, report);
     if institutional_result != SUCCESS {
         log::error!("Institutional sequencing failed: {}", institutional_result);
         return institutional_result;
@@ -289,49 +247,46 @@ pub unsafe fn process_institutional_bundle(bundle: *mut TransactionBundle) -> i3
 
     // Detect arbitrage opportunities
     let detector = CrossChainDetector::new();
-    let opportunities = detector.detect_arbitrage_opportunities(bundle_ref);
-    
-    // Update metrics
-    if let Ok(mut state) = PLUGIN_STATE.lock() {
-        state.bundles_processed += 1;
-        state.total_fees_collected += bundle_ref.metadata.plugin_fees;
+    report.enter(Stage::ArbitrageDetection);
+    let opportunities = detector.detect_arbitrage_opportunities(bundle);
+
+    // The institutional policy sets the minimum fee for V3 bundles
+    report.enter(Stage::FeeCheck);
+    let institutional_fee = calculate_institutional_fee(bundle, opportunities.len());
+    report.fees.institutional_fee = institutional_fee;
+    report.fees.required_fee = report.fees.required_fee.max(institutional_fee);
+    if bundle.metadata.plugin_fees < report.fees.required_fee {
+        let reason = format!(
+            "Insufficient fee for institutional processing: {} < {}",
+            bundle.metadata.plugin_fees,
+            report.fees.required_fee
+        );
+        log::error!("{}", reason);
+        return report.reject(ERROR_INSUFFICIENT_FEE, None, reason);
     }
 
     log::info!(
         "V3 processed bundle: {} txs, {} arbitrage opportunities",
-        bundle_ref.transaction_count,
+        bundle.transactions.len(),
         opportunities.len()
     );
+    report.arbitrage_opportunities = opportunities;
 
     SUCCESS
 }
 
 pub fn get_default_institutional_config() -> InstitutionalConfig {
-    InstitutionalConfig {
-        institution_id: [42u8; 32], // Demo institution ID
-        risk_limits: RiskParameters {
-            max_position_size: 1_000_000_000_000, // 1M USDC equivalent
-            max_daily_volume: 10_000_000_000_000, // 10M USDC equivalent
-            var_limit: 500, // 5%
-        },
-        compliance_requirements: ComplianceFlags {
-            kyc_required: true,
-            aml_screening: true,
-            jurisdiction_restrictions: 0,
-        },
-        cross_chain_enabled: true,
-    }
+    InstitutionalConfig::default()
+}
+
+// The `institutional` section of the plugin config
+pub fn get_institutional_config() -> InstitutionalConfig {
+    PLUGIN_STATE.lock()
+        .map(|state| state.config.institutional.clone())
+        .unwrap_or_default()
 }
 
-// Calculate institutional-specific fees
-pub fn calculate_institutional_fee(bundle: &TransactionBundle, arbitrage_count: usize) -> u64 {
-    let base_fee = 15000; // Base institutional fee (0.015 SOL)
-    let arbitrage_fee = arbitrage_count as u64 * 5000; // 0.005 SOL per arbitrage opportunity
-    let complexity_fee = if bundle.transaction_count > 10 {
-        (bundle.transaction_count as u64 - 10) * 1000 // Additional complexity fee
-    } else {
-        0
-    };
-
-    base_fee + arbitrage_fee + complexity_fee
+// Calculate institutional-specific fees through the configured fee policy
+pub fn calculate_institutional_fee(bundle: &Bundle, arbitrage_count: usize) -> u64 {
+    crate::fees::calculate_institutional_fee(bundle, arbitrage_count)
 }
\ No newline at end of file
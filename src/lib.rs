@@ -3,20 +3,44 @@ use once_cell::sync::Lazy;
 use std::sync::Mutex;
 
 mod types;
-mod processing;
-mod validation;
-mod fees;
-mod metrics;
+pub mod atomicity;
+pub mod block_budget;
+pub mod block_builder;
+pub mod processing;
+pub mod compute_budget;
+pub mod conflicts;
+pub mod validation;
+pub mod fees;
+pub mod congestion;
+pub mod fee_policy;
+pub mod metrics;
+pub mod mev;
+pub mod lookup_tables;
+pub mod owned;
+pub mod report;
+pub mod sandwich;
+pub mod scheduling;
+pub mod tips;
+pub mod trace;
+pub mod wire;
 #[cfg(feature = "oracle")]
 pub mod oracle;
 #[cfg(feature = "oracle")]
-mod pyth_client;
+pub mod pyth_client;
 #[cfg(feature = "oracle")]
-mod oracle_processing;
+pub mod pyth_pull;
+#[cfg(feature = "oracle")]
+pub mod oracle_processing;
+#[cfg(feature = "oracle")]
+pub mod price_refresher;
+#[cfg(feature = "oracle")]
+pub mod mock_rpc;
 #[cfg(feature = "institutional")]
 pub mod institutional;
 
 // Re-export public types and functions
+pub use crate::owned::{AddressTableLookup, Bundle, BundleAttestation, BundleOptions, Instruction, Tx};
+pub use crate::report::{ProcessingReport, Rejection, Stage};
 pub use crate::types::*;
 
 // Global plugin state
@@ -30,7 +54,7 @@ static PLUGIN_NAME: &[u8] = b"RelayPlugin\0";
 // Main plugin interface export
 #[no_mangle]
 pub static PLUGIN_INTERFACE: PluginInterface = PluginInterface {
-    version: 3,
+    version: 4,
     capabilities: CAPABILITY_BUNDLE_PROCESSING | CAPABILITY_FEE_COLLECTION | CAPABILITY_ORACLE_PROCESSING | CAPABILITY_INSTITUTIONAL_MARKET_MAKING,
     name: PLUGIN_NAME.as_ptr() as *const c_char,
     init: plugin_init,
@@ -41,8 +65,11 @@ pub static PLUGIN_INTERFACE: PluginInterface = PluginInterface {
     set_state: set_plugin_state,
 };
 
-// Initialize plugin with configuration
+// Initialize plugin with configuration. Like the other exports below that take
+// host pointers, the signature is fixed by `PluginInterface` and nulls are
+// checked before use
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn plugin_init(config_data: *const u8, config_len: usize) -> i32 {
     // Validate input
     if config_data.is_null() && config_len > 0 {
@@ -52,41 +79,80 @@ pub extern "C" fn plugin_init(config_data: *const u8, config_len: usize) -> i32
     // Load configuration if provided
     if config_len > 0 {
         let config_slice = unsafe { std::slice::from_raw_parts(config_data, config_len) };
-        
-        match serde_json::from_slice::<PluginConfig>(config_slice) {
-            Ok(config) => {
-                if let Ok(mut state) = PLUGIN_STATE.lock() {
-                    state.config = config;
-                    log::info!("Plugin initialized with custom config");
-                } else {
-                    return ERROR_INVALID_STATE;
-                }
-            }
-            Err(e) => {
-                log::error!("Failed to parse config: {}", e);
-                return ERROR_INVALID_STATE;
+
+        // The host reads the reason back from `last_error` in `get_plugin_state`
+        if let Err(e) = configure(config_slice) {
+            log::error!("{}", e);
+            if let Ok(mut state) = PLUGIN_STATE.lock() {
+                state.last_error = Some(e);
             }
+            return ERROR_INVALID_STATE;
         }
     }
 
-    // Initialize oracle client if oracle feature is enabled
+    log::info!("Relay BAM Plugin v{} initialized", env!("CARGO_PKG_VERSION"));
+    SUCCESS
+}
+
+// Apply a plugin config JSON the way `plugin_init` does: validate every
+// section, initialize the Pyth client and start the price refresher from the
+// `oracle` section if there is one, then install the config. Nothing changes
+// if a step fails; a refresher already running keeps running.
+pub fn configure(config_json: &[u8]) -> Result<(), String> {
+    let config = serde_json::from_slice::<PluginConfig>(config_json)
+        .map_err(|e| format!("Failed to parse config: {}", e))?;
+    validate_config(&config).map_err(|e| format!("Invalid configuration: {}", e))?;
+
+    // Taken before the client changes, so a poisoned state leaves it alone
+    let mut state = PLUGIN_STATE.lock().map_err(|_| "Plugin state is poisoned".to_string())?;
+    apply_oracle_config(&config)?;
+    state.config = config;
+    log::info!("Plugin initialized with custom config");
+    Ok(())
+}
+
+// Initialize the Pyth client from the config's `oracle` section, if there is
+// one, and keep its prices fresh in the background; bundles only read the
+// cache. The refresher starts once the client is set up, and if it can't,
+// the previous client goes back. Run with the plugin state locked, just
+// before the config goes in.
+fn apply_oracle_config(config: &PluginConfig) -> Result<(), String> {
     #[cfg(feature = "oracle")]
-    {
-        use crate::oracle::OracleConfig;
-        let _oracle_config = OracleConfig::default();
-        
-        // Note: We can't use async in FFI, so oracle initialization will happen lazily
-        log::info!("Oracle feature enabled, oracle client will initialize on first use");
+    if let Some(oracle_config) = &config.oracle {
+        let previous = pyth_client::configure_pyth_client(oracle_config.clone())
+            .map_err(|e| format!("Failed to initialize oracle client: {}", e))?;
+        if let Err(e) = price_refresher::start_background_refresh() {
+            pyth_client::restore_pyth_client(previous);
+            return Err(format!("Failed to start oracle price refresher: {}", e));
+        }
     }
+    #[cfg(not(feature = "oracle"))]
+    let _ = config;
+    Ok(())
+}
 
-    log::info!("Relay BAM Plugin v{} initialized", env!("CARGO_PKG_VERSION"));
-    SUCCESS
+// Check congestion settings, block limits, MEV pools and the
+// institutional and oracle sections, and build the fee policies once so a
+// bad config is refused up front
+fn validate_config(config: &PluginConfig) -> Result<(), &'static str> {
+    config.congestion.validate()?;
+    config.block_limits.validate()?;
+    config.mev.validate()?;
+    config.institutional.validate()?;
+    #[cfg(feature = "oracle")]
+    if let Some(oracle_config) = &config.oracle {
+        oracle_config.validate()?;
+    }
+    fee_policy::FeeEngine::new(&config.fee_schedule, config.fee_percentage).map(|_| ())
 }
 
 // Shutdown plugin cleanly
 #[no_mangle]
 pub extern "C" fn plugin_shutdown() -> i32 {
     log::info!("Relay BAM Plugin shutting down");
+
+    #[cfg(feature = "oracle")]
+    price_refresher::stop_background_refresh();
     
     // Log final metrics
     if let Ok(state) = PLUGIN_STATE.lock() {
@@ -100,157 +166,405 @@ pub extern "C" fn plugin_shutdown() -> i32 {
     SUCCESS
 }
 
-// Process transaction bundle (V3 with institutional features)
-#[no_mangle]
-pub extern "C" fn process_bundle_v3(bundle: *mut TransactionBundle) -> i32 {
+// Processing pipelines, from plain forwarding up to the institutional
+// features. Pipelines whose feature is compiled out fall back to the next
+// lower one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pipeline {
+    V1,
+    V2,
+    V3,
+}
+
+// Process a bundle through the given pipeline. This is the safe entry point
+// for Rust callers; the `extern "C"` exports convert the host's bundle and
+// call it.
+pub fn process(bundle: &Bundle, pipeline: Pipeline) -> Result<(), i32> {
+    let report = process_with_report(bundle, pipeline);
+
+    match report.result {
+        SUCCESS => Ok(()),
+        code => Err(code),
+    }
+}
+
+// Like `process`, but returns everything the pipeline worked out about the
+// bundle: stages run, fees, suggested ordering and the rejection reason
+pub fn process_with_report(bundle: &Bundle, pipeline: Pipeline) -> ProcessingReport {
+    let mut report = ProcessingReport::new(pipeline);
+    report.transaction_count = bundle.transactions.len();
+
     // Start timing
     let start_time = std::time::Instant::now();
-    
-    // Validate bundle pointer
-    if bundle.is_null() {
-        log::error!("Received null bundle pointer");
-        return ERROR_NULL_POINTER;
+
+    let mut result = run_pipeline(bundle, pipeline, &mut report);
+
+    // Accepted bundles claim their compute in the slot's block, and only
+    // those that fit count as processed
+    if result == SUCCESS {
+        match block_budget::commit_bundle(bundle, &report.included_transactions) {
+            Ok(()) => metrics::record_landed_bundle(bundle.metadata.plugin_fees),
+            Err(rejection) => {
+                report.enter(Stage::BlockLimits);
+                result = report.record(rejection);
+            }
+        }
     }
 
-    // Use institutional processing if available, otherwise fall back to V2/V1
-    #[cfg(feature = "institutional")]
-    let result = unsafe { institutional::process_institutional_bundle(bundle) };
-    
-    #[cfg(all(not(feature = "institutional"), feature = "oracle"))]
-    let result = unsafe { oracle_processing::process_oracle_bundle(bundle) };
-    
-    #[cfg(all(not(feature = "institutional"), not(feature = "oracle")))]
-    let result = unsafe { processing::process_bundle(bundle) };
-    
     // Update metrics
     let processing_time = start_time.elapsed().as_micros() as u64;
     metrics::update_processing_metrics(processing_time, result == SUCCESS);
-    
-    result
+
+    // Landed bundles feed the congestion base fee
+    if result == SUCCESS {
+        let compute_units = fee_policy::FeeContext::from_bundle(bundle).compute_units;
+        congestion::record_bundle(bundle.metadata.slot, compute_units);
+    }
+
+    report.result = result;
+    report.processing_time_us = processing_time;
+    report
 }
 
-// Process transaction bundle (V2 with oracle support)
-#[no_mangle]
-pub extern "C" fn process_bundle_v2(bundle: *mut TransactionBundle) -> i32 {
-    // Start timing
+// Run a bundle through the pipeline without landing it: nothing is claimed
+// in the slot's block, congestion demand is left alone and the bundle isn't
+// counted. Used to weigh candidates before choosing which land.
+pub fn evaluate(bundle: &Bundle, pipeline: Pipeline) -> ProcessingReport {
+    evaluate_report(bundle, ProcessingReport::new(pipeline))
+}
+
+// `evaluate` against a block of the caller's own rather than the slot's
+// live one
+pub(crate) fn evaluate_in(bundle: &Bundle, pipeline: Pipeline, block: block_budget::SimulatedBlock) -> ProcessingReport {
+    let mut report = ProcessingReport::new(pipeline);
+    report.block = Some(block);
+    evaluate_report(bundle, report)
+}
+
+fn evaluate_report(bundle: &Bundle, mut report: ProcessingReport) -> ProcessingReport {
+    let pipeline = report.pipeline;
+    report.transaction_count = bundle.transactions.len();
+
     let start_time = std::time::Instant::now();
-    
-    // Validate bundle pointer
-    if bundle.is_null() {
-        log::error!("Received null bundle pointer");
-        return ERROR_NULL_POINTER;
+    report.result = run_pipeline(bundle, pipeline, &mut report);
+    report.processing_time_us = start_time.elapsed().as_micros() as u64;
+    report
+}
+
+fn run_pipeline(bundle: &Bundle, pipeline: Pipeline, report: &mut ProcessingReport) -> i32 {
+    match pipeline {
+        Pipeline::V1 => processing::process_bundle(bundle, report),
+        Pipeline::V2 => process_v2(bundle, report),
+        Pipeline::V3 => process_v3(bundle, report),
+    }
+}
+
+// Estimate the plugin fee the given pipeline would charge for a bundle
+pub fn estimate_fee(bundle: &Bundle, pipeline: Pipeline) -> u64 {
+    match pipeline {
+        Pipeline::V1 => fees::calculate_bundle_fee(bundle),
+        Pipeline::V2 => estimate_fee_v2(bundle),
+        Pipeline::V3 => estimate_fee_v3(bundle),
     }
+}
 
-    // Use oracle processing if available, otherwise fall back to V1
+// Suggested execution order for a bundle as a permutation of transaction
+// indices, merging priority fees, oracle dependencies and account locks
+pub fn execution_plan(bundle: &Bundle) -> Vec<usize> {
     #[cfg(feature = "oracle")]
-    let result = unsafe { oracle_processing::process_oracle_bundle(bundle) };
-    
+    let oracle_dependent: Vec<usize> = oracle::extract_price_injection_points(bundle)
+        .iter()
+        .map(|point| point.transaction_index)
+        .collect();
+
     #[cfg(not(feature = "oracle"))]
-    let result = unsafe { processing::process_bundle(bundle) };
-    
-    // Update metrics
-    let processing_time = start_time.elapsed().as_micros() as u64;
-    metrics::update_processing_metrics(processing_time, result == SUCCESS);
-    
-    result
+    let oracle_dependent = Vec::new();
+
+    let mut order = scheduling::execution_plan(bundle, &oracle_dependent);
+    sandwich::protect_victims(bundle, &mut order, &sandwich::detect_sandwiches(bundle));
+    order
 }
 
-// Legacy V1 function for backward compatibility
-#[no_mangle]
-pub extern "C" fn process_bundle_forwarding(bundle: *mut TransactionBundle) -> i32 {
-    // Start timing
-    let start_time = std::time::Instant::now();
-    
-    // Validate bundle pointer
-    if bundle.is_null() {
-        log::error!("Received null bundle pointer");
-        return ERROR_NULL_POINTER;
+fn process_v2(bundle: &Bundle, report: &mut ProcessingReport) -> i32 {
+    #[cfg(feature = "oracle")]
+    {
+        oracle_processing::process_oracle_bundle(bundle, report)
     }
 
-    // Perform bundle processing
-    let result = unsafe { processing::process_bundle(bundle) };
-    
-    // Update metrics
-    let processing_time = start_time.elapsed().as_micros() as u64;
-    metrics::update_processing_metrics(processing_time, result == SUCCESS);
-    
-    result
+    #[cfg(not(feature = "oracle"))]
+    {
+        processing::process_bundle(bundle, report)
+    }
 }
 
-// Estimate fee for bundle (V2 with oracle support)
-#[no_mangle]
-pub extern "C" fn estimate_bundle_fee_v2(bundle: *const TransactionBundle) -> u64 {
-    if bundle.is_null() {
-        return 0;
+fn process_v3(bundle: &Bundle, report: &mut ProcessingReport) -> i32 {
+    #[cfg(feature = "institutional")]
+    {
+        institutional::process_institutional_bundle(bundle, report)
     }
 
+    #[cfg(not(feature = "institutional"))]
+    {
+        process_v2(bundle, report)
+    }
+}
+
+fn estimate_fee_v2(bundle: &Bundle) -> u64 {
     #[cfg(feature = "oracle")]
     {
-        oracle_processing::get_oracle_fee_estimate(bundle)
+        oracle_processing::estimate_oracle_fee(bundle)
     }
-    
+
     #[cfg(not(feature = "oracle"))]
     {
-        unsafe { fees::calculate_bundle_fee(bundle) }
+        fees::calculate_bundle_fee(bundle)
     }
 }
 
-// Legacy V1 function for backward compatibility
+fn estimate_fee_v3(bundle: &Bundle) -> u64 {
+    #[cfg(feature = "institutional")]
+    {
+        let detector = institutional::CrossChainDetector::new();
+        let opportunities = detector.detect_arbitrage_opportunities(bundle);
+        let institutional_fee = institutional::calculate_institutional_fee(bundle, opportunities.len());
+        estimate_fee_v2(bundle).max(institutional_fee)
+    }
+
+    #[cfg(not(feature = "institutional"))]
+    {
+        fees::calculate_bundle_fee(bundle)
+    }
+}
+
+fn process_ffi(bundle: *mut TransactionBundle, pipeline: Pipeline) -> i32 {
+    let bundle = match Bundle::from_ffi(bundle) {
+        Ok(bundle) => bundle,
+        Err(code) => return code,
+    };
+
+    match process(&bundle, pipeline) {
+        Ok(()) => SUCCESS,
+        Err(code) => code,
+    }
+}
+
+fn estimate_fee_ffi(bundle: *const TransactionBundle, pipeline: Pipeline) -> u64 {
+    match Bundle::from_ffi(bundle) {
+        Ok(bundle) => estimate_fee(&bundle, pipeline),
+        Err(_) => 0,
+    }
+}
+
+// Process transaction bundle (V3 with institutional features)
 #[no_mangle]
-pub extern "C" fn estimate_forwarding_fee(bundle: *const TransactionBundle) -> u64 {
-    if bundle.is_null() {
-        return 0;
+pub extern "C" fn process_bundle_v3(bundle: *mut TransactionBundle) -> i32 {
+    if !trace::is_recording() {
+        return process_ffi(bundle, Pipeline::V3);
+    }
+
+    let bundle = match Bundle::from_ffi(bundle) {
+        Ok(bundle) => bundle,
+        Err(code) => return code,
+    };
+
+    // Snapshot the inputs before processing moves the congestion state on
+    let record = trace::TraceRecord::capture(&bundle, Pipeline::V3)
+        .map_err(|e| log::error!("Not recording bundle: {}", e))
+        .ok();
+    let result = match process(&bundle, Pipeline::V3) {
+        Ok(()) => SUCCESS,
+        Err(code) => code,
+    };
+    if let Some(mut record) = record {
+        record.result = result;
+        trace::write_record(&record);
     }
 
-    unsafe { fees::calculate_bundle_fee(bundle) }
+    result
 }
 
-// V3 institutional bundle processing
+// Record every bundle `process_bundle_v3` processes, as JSON lines appended
+// to the file at `path`
 #[no_mangle]
-pub extern "C" fn process_institutional_bundle(bundle: *mut TransactionBundle) -> i32 {
-    let start_time = std::time::Instant::now();
-    
-    if bundle.is_null() {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn start_trace_recording(path: *const u8, path_len: usize) -> i32 {
+    if path.is_null() {
         return ERROR_NULL_POINTER;
     }
 
+    let path = unsafe { std::slice::from_raw_parts(path, path_len) };
+    let path = match std::str::from_utf8(path) {
+        Ok(path) => path,
+        Err(_) => return ERROR_INVALID_STATE,
+    };
+
+    match trace::start_recording_to_file(path) {
+        Ok(()) => SUCCESS,
+        Err(e) => {
+            log::error!("Failed to open trace file {}: {}", path, e);
+            ERROR_INVALID_STATE
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn stop_trace_recording() -> i32 {
+    trace::stop_recording();
+    SUCCESS
+}
+
+// Process transaction bundle (V2 with oracle support)
+#[no_mangle]
+pub extern "C" fn process_bundle_v2(bundle: *mut TransactionBundle) -> i32 {
+    process_ffi(bundle, Pipeline::V2)
+}
+
+// Legacy V1 function for backward compatibility
+#[no_mangle]
+pub extern "C" fn process_bundle_forwarding(bundle: *mut TransactionBundle) -> i32 {
+    process_ffi(bundle, Pipeline::V1)
+}
+
+// Estimate fee for bundle (V2 with oracle support)
+#[no_mangle]
+pub extern "C" fn estimate_bundle_fee_v2(bundle: *const TransactionBundle) -> u64 {
+    estimate_fee_ffi(bundle, Pipeline::V2)
+}
+
+// Legacy V1 function for backward compatibility
+#[no_mangle]
+pub extern "C" fn estimate_forwarding_fee(bundle: *const TransactionBundle) -> u64 {
+    estimate_fee_ffi(bundle, Pipeline::V1)
+}
+
+// V3 institutional bundle processing; without the institutional feature this
+// is plain V1 forwarding
+#[no_mangle]
+pub extern "C" fn process_institutional_bundle(bundle: *mut TransactionBundle) -> i32 {
     #[cfg(feature = "institutional")]
-    let result = unsafe { institutional::process_institutional_bundle(bundle) };
-    
+    let pipeline = Pipeline::V3;
+
     #[cfg(not(feature = "institutional"))]
-    let result = unsafe { processing::process_bundle(bundle) };
-    
-    let processing_time = start_time.elapsed().as_micros() as u64;
-    metrics::update_processing_metrics(processing_time, result == SUCCESS);
-    
-    result
+    let pipeline = Pipeline::V1;
+
+    process_ffi(bundle, pipeline)
 }
 
 // V3 institutional fee estimation
 #[no_mangle]
 pub extern "C" fn estimate_institutional_fee(bundle: *const TransactionBundle) -> u64 {
-    if bundle.is_null() {
-        return 0;
+    estimate_fee_ffi(bundle, Pipeline::V3)
+}
+
+// Process a bundle through the V3 pipeline and write a JSON
+// `ProcessingReport` into the caller's buffer. `report_len` always receives
+// the full report size; the report is only copied when it fits. Returns the
+// pipeline result code.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn process_bundle_with_report(
+    bundle: *mut TransactionBundle,
+    report_buffer: *mut u8,
+    buffer_len: usize,
+    report_len: *mut usize,
+) -> i32 {
+    if report_buffer.is_null() || report_len.is_null() {
+        return ERROR_NULL_POINTER;
     }
 
-    #[cfg(feature = "institutional")]
-    {
-        unsafe {
-            let bundle_ref = bundle.as_ref().unwrap();
-            let detector = institutional::CrossChainDetector::new();
-            let opportunities = detector.detect_arbitrage_opportunities(bundle_ref);
-            institutional::calculate_institutional_fee(bundle_ref, opportunities.len())
+    let report = match Bundle::from_ffi(bundle) {
+        Ok(bundle) => process_with_report(&bundle, Pipeline::V3),
+        Err(code) => ProcessingReport::conversion_failure(Pipeline::V3, code),
+    };
+
+    let serialized = match serde_json::to_vec(&report) {
+        Ok(data) => data,
+        Err(_) => return ERROR_INVALID_STATE,
+    };
+
+    unsafe {
+        *report_len = serialized.len();
+        if serialized.len() <= buffer_len {
+            std::ptr::copy_nonoverlapping(serialized.as_ptr(), report_buffer, serialized.len());
         }
     }
-    
-    #[cfg(not(feature = "institutional"))]
-    {
-        unsafe { fees::calculate_bundle_fee(bundle) }
+
+    report.result
+}
+
+// Process a bundle through the V3 pipeline under the inclusion rules in
+// `options_data`, a JSON `BundleOptions`. An empty buffer keeps the
+// defaults: atomic, no reverting transactions, no block budget.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn process_bundle_with_options(
+    bundle: *mut TransactionBundle,
+    options_data: *const u8,
+    options_len: usize,
+) -> i32 {
+    if options_data.is_null() && options_len > 0 {
+        return ERROR_NULL_POINTER;
+    }
+
+    let options = if options_len > 0 {
+        let options_slice = unsafe { std::slice::from_raw_parts(options_data, options_len) };
+        match serde_json::from_slice::<BundleOptions>(options_slice) {
+            Ok(options) => options,
+            Err(e) => {
+                log::error!("Failed to parse bundle options: {}", e);
+                return ERROR_INVALID_BUNDLE;
+            }
+        }
+    } else {
+        BundleOptions::default()
+    };
+
+    let bundle = match Bundle::from_ffi(bundle) {
+        Ok(bundle) => bundle.with_options(options),
+        Err(code) => return code,
+    };
+
+    match process(&bundle, Pipeline::V3) {
+        Ok(()) => SUCCESS,
+        Err(code) => code,
+    }
+}
+
+// Write the suggested execution order into `order_buffer` as transaction
+// indices. Returns the number of entries written.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn get_execution_plan(
+    bundle: *const TransactionBundle,
+    order_buffer: *mut u32,
+    buffer_len: usize,
+) -> i32 {
+    if order_buffer.is_null() {
+        return ERROR_NULL_POINTER;
+    }
+
+    let bundle = match Bundle::from_ffi(bundle) {
+        Ok(bundle) => bundle,
+        Err(code) => return code,
+    };
+
+    let order = execution_plan(&bundle);
+    if order.len() > buffer_len {
+        return ERROR_INVALID_STATE;
+    }
+
+    let order: Vec<u32> = order.into_iter().map(|idx| idx as u32).collect();
+    unsafe {
+        std::ptr::copy_nonoverlapping(order.as_ptr(), order_buffer, order.len());
     }
+
+    order.len() as i32
 }
 
 // Get current plugin state
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn get_plugin_state(state_buffer: *mut u8, buffer_len: usize) -> i32 {
     if state_buffer.is_null() {
         return ERROR_NULL_POINTER;
@@ -277,8 +591,11 @@ pub extern "C" fn get_plugin_state(state_buffer: *mut u8, buffer_len: usize) ->
     serialized.len() as i32
 }
 
-// Set plugin state/config
+// Set plugin state/config. The slot's block usage and congestion demand are
+// runtime state, and the serialized block usage leaves out per-account
+// compute, so both stay as they are.
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn set_plugin_state(state_data: *const u8, data_len: usize) -> i32 {
     if state_data.is_null() {
         return ERROR_NULL_POINTER;
@@ -288,9 +605,22 @@ pub extern "C" fn set_plugin_state(state_data: *const u8, data_len: usize) -> i3
     
     match serde_json::from_slice::<PluginState>(state_slice) {
         Ok(new_state) => {
+            if let Err(e) = validate_config(&new_state.config) {
+                log::error!("Invalid configuration: {}", e);
+                return ERROR_INVALID_STATE;
+            }
+
             match PLUGIN_STATE.lock() {
                 Ok(mut state) => {
-                    *state = new_state;
+                    if let Err(e) = apply_oracle_config(&new_state.config) {
+                        log::error!("{}", e);
+                        return ERROR_INVALID_STATE;
+                    }
+                    *state = PluginState {
+                        block_budget: std::mem::take(&mut state.block_budget),
+                        congestion: std::mem::take(&mut state.congestion),
+                        ..new_state
+                    };
                     SUCCESS
                 }
                 Err(_) => ERROR_INVALID_STATE,
@@ -300,10 +630,38 @@ pub extern "C" fn set_plugin_state(state_data: *const u8, data_len: usize) -> i3
     }
 }
 
+// Supply an address lookup table used to resolve v0 messages
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn register_address_lookup_table(
+    table_key: *const Pubkey,
+    addresses: *const Pubkey,
+    address_count: usize,
+) -> i32 {
+    if table_key.is_null() || (addresses.is_null() && address_count > 0) {
+        return ERROR_NULL_POINTER;
+    }
+
+    let table_key = unsafe { *table_key };
+    let addresses = if address_count == 0 {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(addresses, address_count).to_vec() }
+    };
+
+    match lookup_tables::register_lookup_table(table_key, addresses) {
+        Ok(()) => SUCCESS,
+        Err(e) => {
+            log::warn!("Rejected address lookup table: {}", e);
+            ERROR_INVALID_STATE
+        }
+    }
+}
+
 // Export additional utility functions
 #[no_mangle]
 pub extern "C" fn relay_plugin_version() -> u32 {
-    3
+    PLUGIN_INTERFACE.version
 }
 
 #[no_mangle]
@@ -327,13 +685,26 @@ pub extern "C" fn relay_plugin_capabilities() -> u32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
     use std::time::Instant;
 
+    // Backing storage that must outlive the raw pointers inside the transaction
+    type TestTransaction = (Vec<Signature>, Vec<Pubkey>, Vec<CompiledInstruction>, Vec<u8>, Vec<u8>, Transaction);
+
+    // Sign the message with the transaction's single required signer
+    fn sign_test_transaction(signer: &SigningKey, tx: &mut Transaction) -> Vec<Signature> {
+        let unsigned = Transaction { signature_count: 0, ..tx.clone() };
+        let message = unsafe { owned::Tx::from_raw(&unsigned) }.unwrap().message_bytes().unwrap();
+        let signatures = vec![Signature { bytes: signer.sign(&message).to_bytes() }];
+        tx.signatures = signatures.as_ptr() as *mut Signature;
+        signatures
+    }
+
     // Helper to create test data
-    fn create_test_transaction() -> (Vec<Signature>, Vec<Pubkey>, Vec<CompiledInstruction>, Vec<u8>, Vec<u8>, Transaction) {
-        let signatures = vec![Signature { bytes: [1u8; 64] }];
+    fn create_test_transaction() -> TestTransaction {
+        let signer = SigningKey::from_bytes(&[1u8; 32]);
         let account_keys = vec![
-            Pubkey { bytes: [1u8; 32] },  // Program
+            Pubkey { bytes: signer.verifying_key().to_bytes() },  // Program / fee payer
             Pubkey { bytes: [2u8; 32] },  // Signer
             Pubkey { bytes: [3u8; 32] },  // Destination
         ];
@@ -348,10 +719,11 @@ mod tests {
             data_len: 4,
         }];
         
-        let transaction = Transaction {
-            signatures: signatures.as_ptr() as *mut Signature,
+        let mut transaction = Transaction {
+            signatures: std::ptr::null_mut(),
             signature_count: 1,
             message: TransactionMessage {
+                version: MESSAGE_VERSION_LEGACY,
                 header: MessageHeader {
                     num_required_signatures: 1,
                     num_readonly_signed_accounts: 0,
@@ -362,10 +734,13 @@ mod tests {
                 recent_blockhash: [1u8; 32],
                 instructions: instructions.as_ptr() as *mut CompiledInstruction,
                 instructions_count: 1,
+                address_table_lookups: std::ptr::null_mut(),
+                address_table_lookups_count: 0,
             },
             priority_fee: 5000,
             compute_limit: 200000,
         };
+        let signatures = sign_test_transaction(&signer, &mut transaction);
         
         (signatures, account_keys, instructions, accounts_data, instruction_data, transaction)
     }
@@ -390,7 +765,7 @@ mod tests {
 
     #[test]
     fn test_plugin_version() {
-        assert_eq!(relay_plugin_version(), 3);
+        assert_eq!(relay_plugin_version(), 4);
         
         let capabilities = relay_plugin_capabilities();
         assert!(capabilities & CAPABILITY_BUNDLE_PROCESSING != 0);
@@ -403,6 +778,33 @@ mod tests {
         assert!(capabilities & CAPABILITY_INSTITUTIONAL_MARKET_MAKING != 0);
     }
 
+    // Interface version 4 layout on 64-bit hosts. The message version and
+    // lookup table fields grew TransactionMessage, which moved everything
+    // after it in Transaction; a change here means a new interface version.
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_ffi_layout() {
+        use std::mem::{offset_of, size_of};
+
+        assert_eq!(size_of::<TransactionMessage>(), 88);
+        assert_eq!(offset_of!(TransactionMessage, header), 0);
+        assert_eq!(offset_of!(TransactionMessage, account_keys), 8);
+        assert_eq!(offset_of!(TransactionMessage, account_keys_count), 16);
+        assert_eq!(offset_of!(TransactionMessage, recent_blockhash), 17);
+        assert_eq!(offset_of!(TransactionMessage, instructions), 56);
+        assert_eq!(offset_of!(TransactionMessage, instructions_count), 64);
+        assert_eq!(offset_of!(TransactionMessage, version), 65);
+        assert_eq!(offset_of!(TransactionMessage, address_table_lookups), 72);
+        assert_eq!(offset_of!(TransactionMessage, address_table_lookups_count), 80);
+
+        assert_eq!(size_of::<Transaction>(), 120);
+        assert_eq!(offset_of!(Transaction, signatures), 0);
+        assert_eq!(offset_of!(Transaction, signature_count), 8);
+        assert_eq!(offset_of!(Transaction, message), 16);
+        assert_eq!(offset_of!(Transaction, priority_fee), 104);
+        assert_eq!(offset_of!(Transaction, compute_limit), 112);
+    }
+
     #[test]
     fn test_v1_production_verification() {
         println!("\n🔍 V1 PRODUCTION VERIFICATION");
@@ -410,15 +812,13 @@ mod tests {
         
         // Test plugin interface
         println!("✅ Plugin interface verification...");
-        unsafe {
-            assert_eq!(PLUGIN_INTERFACE.version, 3);
-            let caps = PLUGIN_INTERFACE.capabilities;
-            assert!(caps & CAPABILITY_BUNDLE_PROCESSING != 0);
-            assert!(caps & CAPABILITY_FEE_COLLECTION != 0);
-            assert!(caps & CAPABILITY_ORACLE_PROCESSING != 0);
-            assert!(caps & CAPABILITY_INSTITUTIONAL_MARKET_MAKING != 0);
-            assert!(!PLUGIN_INTERFACE.name.is_null());
-        }
+        assert_eq!(PLUGIN_INTERFACE.version, 4);
+        let caps = PLUGIN_INTERFACE.capabilities;
+        assert!(caps & CAPABILITY_BUNDLE_PROCESSING != 0);
+        assert!(caps & CAPABILITY_FEE_COLLECTION != 0);
+        assert!(caps & CAPABILITY_ORACLE_PROCESSING != 0);
+        assert!(caps & CAPABILITY_INSTITUTIONAL_MARKET_MAKING != 0);
+        assert!(!PLUGIN_INTERFACE.name.is_null());
         
         // Test initialization with config
         println!("✅ Configuration testing...");
@@ -517,7 +917,7 @@ mod tests {
         
         // Test state management
         println!("✅ State management testing...");
-        let mut buffer = vec![0u8; 1024];
+        let mut buffer = vec![0u8; 2048];
         let state_len = get_plugin_state(buffer.as_mut_ptr(), buffer.len());
         assert!(state_len > 0);
         
@@ -572,7 +972,7 @@ mod tests {
         
         // Test oracle interface exists
         println!("✅ Oracle interface verification...");
-        assert_eq!(PLUGIN_INTERFACE.version, 3);
+        assert_eq!(PLUGIN_INTERFACE.version, 4);
         assert!(
             PLUGIN_INTERFACE.capabilities & CAPABILITY_ORACLE_PROCESSING != 0,
             "Oracle processing capability not found"
@@ -615,7 +1015,7 @@ mod tests {
         println!("✅ Price injection point detection...");
         let (_sigs, _keys, _instrs, _acc_data, _inst_data, mut tx) = create_test_transaction();
         
-        let mut bundle = TransactionBundle {
+        let bundle = TransactionBundle {
             transaction_count: 1,
             transactions: &mut tx as *mut Transaction,
             metadata: BundleMetadata {
@@ -631,7 +1031,8 @@ mod tests {
             attestation: std::ptr::null_mut(),
         };
         
-        let injection_points = extract_price_injection_points(&bundle);
+        let owned_bundle = unsafe { Bundle::from_raw(&bundle) }.unwrap();
+        let injection_points = extract_price_injection_points(&owned_bundle);
         println!("    Found {} potential injection points", injection_points.len());
         
         // Test V2 fee estimation
@@ -704,6 +1105,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_error_code_consistency() {
         // Verify error codes are properly defined and unique
         assert_eq!(SUCCESS, 0);
@@ -725,7 +1127,7 @@ mod tests {
         
         // Test plugin version and capabilities
         println!("✅ V3 interface verification...");
-        assert_eq!(relay_plugin_version(), 3);
+        assert_eq!(relay_plugin_version(), 4);
         
         let capabilities = relay_plugin_capabilities();
         assert!(capabilities & CAPABILITY_INSTITUTIONAL_MARKET_MAKING != 0);
@@ -758,7 +1160,8 @@ mod tests {
         // Test cross-chain detection
         println!("✅ Cross-chain arbitrage detection...");
         let detector = institutional::CrossChainDetector::new();
-        let opportunities = unsafe { detector.detect_arbitrage_opportunities(&bundle) };
+        let owned_bundle = unsafe { Bundle::from_raw(&bundle) }.unwrap();
+        let opportunities = detector.detect_arbitrage_opportunities(&owned_bundle);
         println!("    Found {} arbitrage opportunities", opportunities.len());
         
         // Test institutional fee calculation
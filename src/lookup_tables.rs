@@ -0,0 +1,133 @@
+use crate::owned::Tx;
+use crate::types::*;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+// On-chain lookup tables hold at most 256 addresses
+pub const MAX_LOOKUP_TABLE_ADDRESSES: usize = 256;
+
+// Lookup tables supplied by the host. The plugin has no chain access, so v0
+// messages can only be resolved against tables registered here.
+static LOOKUP_TABLES: Lazy<RwLock<HashMap<Pubkey, Vec<Pubkey>>>> = Lazy::new(|| {
+    RwLock::new(HashMap::new())
+});
+
+pub fn register_lookup_table(table_key: Pubkey, addresses: Vec<Pubkey>) -> Result<(), &'static str> {
+    if addresses.len() > MAX_LOOKUP_TABLE_ADDRESSES {
+        return Err("Lookup table exceeds maximum address count");
+    }
+
+    let mut tables = LOOKUP_TABLES.write().map_err(|_| "Lookup table store poisoned")?;
+    tables.insert(table_key, addresses);
+    Ok(())
+}
+
+pub fn remove_lookup_table(table_key: &Pubkey) -> bool {
+    match LOOKUP_TABLES.write() {
+        Ok(mut tables) => tables.remove(table_key).is_some(),
+        Err(_) => false,
+    }
+}
+
+pub fn clear_lookup_tables() {
+    if let Ok(mut tables) = LOOKUP_TABLES.write() {
+        tables.clear();
+    }
+}
+
+pub fn get_lookup_table(table_key: &Pubkey) -> Option<Vec<Pubkey>> {
+    LOOKUP_TABLES.read().ok()?.get(table_key).cloned()
+}
+
+// Every registered table, to put back with `restore_lookup_tables`
+pub fn lookup_tables() -> HashMap<Pubkey, Vec<Pubkey>> {
+    LOOKUP_TABLES.read().map(|tables| tables.clone()).unwrap_or_default()
+}
+
+pub fn restore_lookup_tables(saved: HashMap<Pubkey, Vec<Pubkey>>) {
+    if let Ok(mut tables) = LOOKUP_TABLES.write() {
+        *tables = saved;
+    }
+}
+
+pub fn lookup_table_count() -> usize {
+    LOOKUP_TABLES.read().map(|tables| tables.len()).unwrap_or(0)
+}
+
+// Full account list of a message, in the order instruction indices refer to:
+// static keys, then writable loaded addresses, then readonly loaded addresses
+#[derive(Debug, Clone)]
+pub struct LoadedAccountKeys {
+    pub keys: Vec<Pubkey>,
+    pub num_static: usize,
+    pub num_writable_loaded: usize,
+}
+
+impl LoadedAccountKeys {
+    pub fn get(&self, index: u8) -> Option<&Pubkey> {
+        self.keys.get(index as usize)
+    }
+
+    pub fn is_loaded_writable(&self, index: usize) -> bool {
+        index >= self.num_static && index < self.num_static + self.num_writable_loaded
+    }
+
+    // Static keys are split by the header: writable signers, readonly
+    // signers, writable non-signers, readonly non-signers
+    pub fn is_writable(&self, index: usize, header: &MessageHeader) -> bool {
+        if index >= self.num_static {
+            return self.is_loaded_writable(index);
+        }
+
+        let num_signed = header.num_required_signatures as usize;
+        if index < num_signed {
+            index < num_signed.saturating_sub(header.num_readonly_signed_accounts as usize)
+        } else {
+            index < self.num_static.saturating_sub(header.num_readonly_unsigned_accounts as usize)
+        }
+    }
+}
+
+// Resolve a transaction's account indices against the registered lookup tables
+pub fn resolve_account_keys(tx: &Tx) -> Result<LoadedAccountKeys, &'static str> {
+    let mut keys = tx.account_keys.clone();
+    let num_static = keys.len();
+
+    if tx.version == MESSAGE_VERSION_LEGACY || tx.address_table_lookups.is_empty() {
+        return Ok(LoadedAccountKeys {
+            keys,
+            num_static,
+            num_writable_loaded: 0,
+        });
+    }
+
+    let tables = LOOKUP_TABLES.read().map_err(|_| "Lookup table store poisoned")?;
+
+    let mut readonly = Vec::new();
+    for lookup in &tx.address_table_lookups {
+        let table = tables
+            .get(&lookup.account_key)
+            .ok_or("Address lookup table not found")?;
+
+        for &index in &lookup.writable_indexes {
+            keys.push(*table.get(index as usize).ok_or("Lookup table index out of range")?);
+        }
+        for &index in &lookup.readonly_indexes {
+            readonly.push(*table.get(index as usize).ok_or("Lookup table index out of range")?);
+        }
+    }
+
+    let num_writable_loaded = keys.len() - num_static;
+    keys.extend(readonly);
+
+    if keys.len() > u8::MAX as usize + 1 {
+        return Err("Too many account keys after lookup resolution");
+    }
+
+    Ok(LoadedAccountKeys {
+        keys,
+        num_static,
+        num_writable_loaded,
+    })
+}
@@ -33,6 +33,14 @@ pub fn update_processing_metrics(processing_time_us: u64, success: bool) {
     }
 }
 
+// Count a bundle that landed, with the plugin fee it paid
+pub fn record_landed_bundle(plugin_fees: u64) {
+    if let Ok(mut state) = PLUGIN_STATE.lock() {
+        state.bundles_processed += 1;
+        state.total_fees_collected = state.total_fees_collected.saturating_add(plugin_fees);
+    }
+}
+
 pub fn get_current_metrics() -> MetricsSnapshot {
     let state = PLUGIN_STATE.lock().unwrap();
     
@@ -0,0 +1,499 @@
+use crate::lookup_tables;
+use crate::owned::{Bundle, Instruction, Tx};
+use crate::types::*;
+use crate::PLUGIN_STATE;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8
+pub const RAYDIUM_AMM_V4_PROGRAM_ID: Pubkey = Pubkey {
+    bytes: [
+        75, 217, 73, 196, 54, 2, 195, 63, 32, 119, 144, 237, 22, 163, 82, 76,
+        161, 185, 151, 92, 241, 33, 162, 169, 12, 255, 236, 125, 248, 182, 138, 205,
+    ],
+};
+
+// CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C
+pub const RAYDIUM_CPMM_PROGRAM_ID: Pubkey = Pubkey {
+    bytes: [
+        169, 42, 90, 139, 79, 41, 89, 82, 132, 37, 80, 170, 147, 253, 91, 149,
+        181, 172, 230, 168, 235, 146, 12, 147, 148, 46, 67, 105, 12, 32, 236, 115,
+    ],
+};
+
+// whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc
+pub const ORCA_WHIRLPOOL_PROGRAM_ID: Pubkey = Pubkey {
+    bytes: [
+        14, 3, 104, 95, 142, 144, 144, 83, 228, 88, 18, 28, 102, 245, 167, 106,
+        237, 199, 112, 106, 161, 28, 130, 248, 170, 149, 42, 143, 43, 120, 121, 169,
+    ],
+};
+
+// Raydium AMM v4 instruction tags
+const RAYDIUM_SWAP_BASE_IN: u8 = 9;
+const RAYDIUM_SWAP_BASE_OUT: u8 = 11;
+
+// Anchor discriminators: sha256("global:<name>")[..8]
+const CPMM_SWAP_BASE_INPUT: [u8; 8] = [143, 190, 90, 218, 196, 30, 51, 222];
+const CPMM_SWAP_BASE_OUTPUT: [u8; 8] = [55, 217, 98, 86, 163, 74, 180, 173];
+const WHIRLPOOL_SWAP: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+const WHIRLPOOL_SWAP_V2: [u8; 8] = [43, 4, 237, 11, 26, 201, 30, 98];
+
+// An attacker pushing the price further than this pays the pool more than
+// it takes from the victim, so larger slippage tolerances don't add value
+const MAX_EXTRACTABLE_SLIPPAGE: f64 = 0.10;
+
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Amm {
+    RaydiumAmmV4,
+    RaydiumCpmm,
+    OrcaWhirlpool,
+}
+
+// A decoded swap. Exact-out swaps are normalised to their maximum input and
+// exact output, so `min_amount_out / amount_in` is always the worst rate the
+// trader accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Swap {
+    pub transaction_index: usize,
+    pub instruction_index: usize,
+    pub amm: Amm,
+    pub pool: Pubkey,
+    // Fee payer of the swapping transaction
+    pub signer: Pubkey,
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+    // Some(true) when selling the pool's token A (the configured base
+    // token); None when the instruction doesn't say
+    pub a_to_b: Option<bool>,
+    // The trader's token accounts and, where the instruction names it, the
+    // mint being sold
+    pub source: Option<Pubkey>,
+    pub destination: Option<Pubkey>,
+    pub input_mint: Option<Pubkey>,
+}
+
+impl Swap {
+    // Whether two swaps on one pool trade the same way, or None when the
+    // instructions don't tell
+    pub fn same_direction(&self, other: &Swap) -> Option<bool> {
+        if let (Some(a), Some(b)) = (self.a_to_b, other.a_to_b) {
+            return Some(a == b);
+        }
+        if let (Some(a), Some(b)) = (self.input_mint, other.input_mint) {
+            return Some(a == b);
+        }
+
+        // One trader's legs: spending what the other leg bought is the
+        // opposite direction
+        match (self.source, self.destination, other.source, other.destination) {
+            (Some(source), Some(destination), Some(other_source), Some(other_destination)) => {
+                if source == other_source && destination == other_destination {
+                    Some(true)
+                } else if source == other_destination && destination == other_source {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+// A pool the estimator can price. Token A is the base token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AmmPoolConfig {
+    pub address: Pubkey,
+    // Pyth price accounts quoting each token in USD
+    pub base_price_account: Pubkey,
+    pub quote_price_account: Pubkey,
+    pub base_decimals: u8,
+    pub quote_decimals: u8,
+    // Total pool depth in USD, for constant-product price impact
+    pub liquidity_usd: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MevConfig {
+    // Converts USD values to lamports
+    pub sol_price_account: Pubkey,
+    pub pools: Vec<AmmPoolConfig>,
+}
+
+impl Default for MevConfig {
+    fn default() -> Self {
+        Self {
+            sol_price_account: Pubkey::from_base58("Gnt27xtC473ZT2Mw5u8wZ68Z3gULkSTb5DuxJy7eJotD").unwrap(), // SOL/USD
+            pools: Vec::new(),
+        }
+    }
+}
+
+impl MevConfig {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        for pool in &self.pools {
+            if !pool.liquidity_usd.is_finite() || pool.liquidity_usd <= 0.0 {
+                return Err("AMM pool liquidity must be positive");
+            }
+        }
+        Ok(())
+    }
+}
+
+// USD prices by price feed id. The oracle cache is the live source; tests
+// use a plain map.
+pub trait PriceSource {
+    fn usd_price(&self, price_id: &[u8; 32]) -> Option<f64>;
+}
+
+impl PriceSource for HashMap<[u8; 32], f64> {
+    fn usd_price(&self, price_id: &[u8; 32]) -> Option<f64> {
+        self.get(price_id).copied()
+    }
+}
+
+// Fresh prices from the Pyth client's cache
+#[cfg(feature = "oracle")]
+pub struct OracleCachePrices;
+
+#[cfg(feature = "oracle")]
+impl PriceSource for OracleCachePrices {
+    fn usd_price(&self, price_id: &[u8; 32]) -> Option<f64> {
+        crate::pyth_client::peek_cached_price(price_id)
+            .map(|price_data| price_data.price as f64 * 10f64.powi(price_data.expo))
+    }
+}
+
+// Without the oracle there is nothing to price swaps with
+#[cfg(not(feature = "oracle"))]
+pub struct OracleCachePrices;
+
+#[cfg(not(feature = "oracle"))]
+impl PriceSource for OracleCachePrices {
+    fn usd_price(&self, _price_id: &[u8; 32]) -> Option<f64> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SwapEstimate {
+    pub transaction_index: usize,
+    pub instruction_index: usize,
+    pub amm: Amm,
+    pub a_to_b: bool,
+    pub notional_usd: f64,
+    // Fraction of the input the pool's curve takes
+    pub price_impact: f64,
+    // How far below the expected output the trader's minimum sits
+    pub slippage_tolerance: f64,
+    // Lamports a front-run/back-run pair could take from this swap
+    pub sandwich_value: u64,
+    // Lamports an arbitrageur gets restoring the pool price afterwards
+    pub backrun_value: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MevEstimate {
+    pub swaps: Vec<SwapEstimate>,
+    // Sandwich plus back-run value of each transaction's swaps
+    pub transaction_mev: Vec<u64>,
+    pub total: u64,
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+// What a swap instruction's data says, plus where its accounts sit
+struct DecodedSwap {
+    amm: Amm,
+    amount_in: u64,
+    min_amount_out: u64,
+    a_to_b: Option<bool>,
+    // Positions in the instruction's account list
+    pool: usize,
+    source: Option<usize>,
+    destination: Option<usize>,
+    input_mint: Option<usize>,
+}
+
+fn decode_swap_instruction(program: &Pubkey, data: &[u8], account_count: usize) -> Option<DecodedSwap> {
+    if *program == RAYDIUM_AMM_V4_PROGRAM_ID {
+        // tag, then two u64s: in/min-out for base-in, max-in/out for base-out
+        let first = read_u64(data, 1)?;
+        let second = read_u64(data, 9)?;
+        if data[0] != RAYDIUM_SWAP_BASE_IN && data[0] != RAYDIUM_SWAP_BASE_OUT {
+            return None;
+        }
+        // Both account layouts end with user source, destination, owner
+        return Some(DecodedSwap {
+            amm: Amm::RaydiumAmmV4,
+            amount_in: first,
+            min_amount_out: second,
+            a_to_b: None,
+            pool: 1,
+            source: account_count.checked_sub(3),
+            destination: account_count.checked_sub(2),
+            input_mint: None,
+        });
+    }
+
+    let discriminator: [u8; 8] = data.get(0..8)?.try_into().ok()?;
+
+    if *program == RAYDIUM_CPMM_PROGRAM_ID {
+        let first = read_u64(data, 8)?;
+        let second = read_u64(data, 16)?;
+        if discriminator != CPMM_SWAP_BASE_INPUT && discriminator != CPMM_SWAP_BASE_OUTPUT {
+            return None;
+        }
+        return Some(DecodedSwap {
+            amm: Amm::RaydiumCpmm,
+            amount_in: first,
+            min_amount_out: second,
+            a_to_b: None,
+            pool: 3,
+            source: Some(4),
+            destination: Some(5),
+            input_mint: Some(10),
+        });
+    }
+
+    if *program == ORCA_WHIRLPOOL_PROGRAM_ID {
+        // amount, other_amount_threshold, sqrt_price_limit (u128),
+        // amount_specified_is_input, a_to_b. The owner's token A and B
+        // accounts sit right after the pool (swap) or the mints (swap_v2).
+        let (pool, owner_account_a) = match discriminator {
+            WHIRLPOOL_SWAP => (2, 3),
+            WHIRLPOOL_SWAP_V2 => (4, 7),
+            _ => return None,
+        };
+        let amount = read_u64(data, 8)?;
+        let threshold = read_u64(data, 16)?;
+        let amount_is_input = *data.get(40)? != 0;
+        let a_to_b = *data.get(41)? != 0;
+        let (amount_in, min_amount_out) = if amount_is_input { (amount, threshold) } else { (threshold, amount) };
+        let owner_account_b = owner_account_a + 2;
+        let (source, destination) = if a_to_b {
+            (owner_account_a, owner_account_b)
+        } else {
+            (owner_account_b, owner_account_a)
+        };
+        return Some(DecodedSwap {
+            amm: Amm::OrcaWhirlpool,
+            amount_in,
+            min_amount_out,
+            a_to_b: Some(a_to_b),
+            pool,
+            source: Some(source),
+            destination: Some(destination),
+            input_mint: None,
+        });
+    }
+
+    None
+}
+
+pub fn decode_swap(
+    tx: &Tx,
+    transaction_index: usize,
+    instruction_index: usize,
+    instruction: &Instruction,
+    account_keys: &lookup_tables::LoadedAccountKeys,
+) -> Option<Swap> {
+    // Programs can't come from lookup tables, so static keys suffice
+    let program = tx.account_keys.get(instruction.program_id_index as usize)?;
+    let decoded = decode_swap_instruction(program, &instruction.data, instruction.accounts.len())?;
+    let account = |position: Option<usize>| {
+        position
+            .and_then(|position| instruction.accounts.get(position))
+            .and_then(|&index| account_keys.get(index))
+            .copied()
+    };
+
+    Some(Swap {
+        transaction_index,
+        instruction_index,
+        amm: decoded.amm,
+        pool: account(Some(decoded.pool))?,
+        signer: *tx.account_keys.first()?,
+        amount_in: decoded.amount_in,
+        min_amount_out: decoded.min_amount_out,
+        a_to_b: decoded.a_to_b,
+        source: account(decoded.source),
+        destination: account(decoded.destination),
+        input_mint: account(decoded.input_mint),
+    })
+}
+
+fn is_amm_instruction(tx: &Tx, instruction: &Instruction) -> bool {
+    matches!(
+        tx.account_keys.get(instruction.program_id_index as usize),
+        Some(program) if [RAYDIUM_AMM_V4_PROGRAM_ID, RAYDIUM_CPMM_PROGRAM_ID, ORCA_WHIRLPOOL_PROGRAM_ID].contains(program)
+    )
+}
+
+// Every swap on a recognised AMM, in bundle order
+pub fn detect_swaps(bundle: &Bundle) -> Vec<Swap> {
+    let mut swaps = Vec::new();
+
+    for (tx_idx, tx) in bundle.transactions.iter().enumerate() {
+        if !tx.instructions.iter().any(|instruction| is_amm_instruction(tx, instruction)) {
+            continue;
+        }
+
+        // Pools may be loaded through lookup tables
+        let account_keys = match lookup_tables::resolve_account_keys(tx) {
+            Ok(keys) => keys,
+            Err(e) => {
+                log::debug!("Skipping swap scan for tx {}: {}", tx_idx, e);
+                continue;
+            }
+        };
+
+        for (inst_idx, instruction) in tx.instructions.iter().enumerate() {
+            if let Some(swap) = decode_swap(tx, tx_idx, inst_idx, instruction, &account_keys) {
+                swaps.push(swap);
+            }
+        }
+    }
+
+    swaps
+}
+
+struct PricedPool {
+    base_usd: f64,
+    quote_usd: f64,
+    base_scale: f64,
+    quote_scale: f64,
+    liquidity_usd: f64,
+}
+
+fn price_pool(pool: &AmmPoolConfig, prices: &dyn PriceSource) -> Option<PricedPool> {
+    Some(PricedPool {
+        base_usd: prices.usd_price(&pool.base_price_account.bytes).filter(|price| *price > 0.0)?,
+        quote_usd: prices.usd_price(&pool.quote_price_account.bytes).filter(|price| *price > 0.0)?,
+        base_scale: 10f64.powi(pool.base_decimals as i32),
+        quote_scale: 10f64.powi(pool.quote_decimals as i32),
+        liquidity_usd: pool.liquidity_usd,
+    })
+}
+
+// USD value of the input and of the minimum output when trading in the
+// given direction
+fn swap_usd(swap: &Swap, pool: &PricedPool, a_to_b: bool) -> (f64, f64) {
+    let base = |amount: u64| amount as f64 / pool.base_scale * pool.base_usd;
+    let quote = |amount: u64| amount as f64 / pool.quote_scale * pool.quote_usd;
+
+    if a_to_b {
+        (base(swap.amount_in), quote(swap.min_amount_out))
+    } else {
+        (quote(swap.amount_in), base(swap.min_amount_out))
+    }
+}
+
+// When the instruction doesn't give the direction, take the one whose worst
+// accepted rate is closest to the oracle rate
+fn infer_direction(swap: &Swap, pool: &PricedPool) -> bool {
+    let distance = |a_to_b: bool| {
+        let (input, min_output) = swap_usd(swap, pool, a_to_b);
+        if input <= 0.0 || min_output <= 0.0 {
+            return f64::INFINITY;
+        }
+        (min_output / input).ln().abs()
+    };
+
+    distance(true) <= distance(false)
+}
+
+// Constant-product estimate: the input side holds half the pool's depth, so
+// a trade of x against reserve r loses x / (r + x) to the curve and leaves
+// the pool mispriced by about that much for a back-runner to close
+pub fn estimate_swap(swap: &Swap, pool: &AmmPoolConfig, prices: &dyn PriceSource, sol_usd: f64) -> Option<SwapEstimate> {
+    let priced = price_pool(pool, prices)?;
+    let a_to_b = swap.a_to_b.unwrap_or_else(|| infer_direction(swap, &priced));
+    let (notional_usd, min_output_usd) = swap_usd(swap, &priced, a_to_b);
+
+    let reserve_usd = priced.liquidity_usd / 2.0;
+    let price_impact = notional_usd / (reserve_usd + notional_usd);
+    let expected_output_usd = notional_usd * (1.0 - price_impact);
+    let slippage_tolerance = if expected_output_usd > 0.0 {
+        (1.0 - min_output_usd / expected_output_usd).clamp(0.0, MAX_EXTRACTABLE_SLIPPAGE)
+    } else {
+        0.0
+    };
+
+    let to_lamports = |usd: f64| (usd / sol_usd * LAMPORTS_PER_SOL) as u64;
+
+    Some(SwapEstimate {
+        transaction_index: swap.transaction_index,
+        instruction_index: swap.instruction_index,
+        amm: swap.amm,
+        a_to_b,
+        notional_usd,
+        price_impact,
+        slippage_tolerance,
+        sandwich_value: to_lamports(expected_output_usd * slippage_tolerance),
+        backrun_value: to_lamports(notional_usd * price_impact / 2.0),
+    })
+}
+
+// Swaps on pools that aren't configured or can't be priced are left out
+pub fn estimate_mev(bundle: &Bundle, config: &MevConfig, prices: &dyn PriceSource) -> MevEstimate {
+    let mut estimate = MevEstimate {
+        transaction_mev: vec![0; bundle.transactions.len()],
+        ..Default::default()
+    };
+
+    if config.pools.is_empty() {
+        return estimate;
+    }
+
+    let sol_usd = match prices.usd_price(&config.sol_price_account.bytes)
+        .filter(|price| *price > 0.0)
+    {
+        Some(price) => price,
+        None => {
+            log::debug!("No SOL price, skipping MEV estimation");
+            return estimate;
+        }
+    };
+
+    for swap in detect_swaps(bundle) {
+        let pool = match config.pools.iter().find(|pool| pool.address == swap.pool) {
+            Some(pool) => pool,
+            None => continue,
+        };
+
+        let swap_estimate = match estimate_swap(&swap, pool, prices, sol_usd) {
+            Some(swap_estimate) => swap_estimate,
+            None => {
+                log::debug!("No prices for pool in tx {}, skipping", swap.transaction_index);
+                continue;
+            }
+        };
+
+        let value = swap_estimate.sandwich_value.saturating_add(swap_estimate.backrun_value);
+        let tx_mev = &mut estimate.transaction_mev[swap.transaction_index];
+        *tx_mev = tx_mev.saturating_add(value);
+        estimate.total = estimate.total.saturating_add(value);
+        estimate.swaps.push(swap_estimate);
+    }
+
+    estimate
+}
+
+// MEV estimate for a bundle under the current config, priced from the
+// oracle cache
+pub fn estimate_bundle_mev(bundle: &Bundle) -> MevEstimate {
+    let config = match PLUGIN_STATE.lock() {
+        Ok(state) => state.config.mev.clone(),
+        Err(_) => return MevEstimate { transaction_mev: vec![0; bundle.transactions.len()], ..Default::default() },
+    };
+
+    estimate_mev(bundle, &config, &OracleCachePrices)
+}
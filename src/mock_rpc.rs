@@ -0,0 +1,268 @@
+// A stand-in for a Solana JSON-RPC node, for exercising the Pyth client
+// offline. It listens on localhost, answers `getAccountInfo` and
+// `getMultipleAccounts` from a table of canned responses and records the
+// requests it sees. One thread per connection, so a delayed response doesn't
+// hold up the others.
+
+use crate::pyth_client::{
+    CONF_OFFSET, EXPO_OFFSET, MIN_PRICE_ACCOUNT_SIZE, PRICE_OFFSET, PYTH_ACCOUNT_TYPE_PRICE, PYTH_MAGIC_NUMBER,
+    PYTH_VERSION_V2, TIMESTAMP_OFFSET,
+};
+use crate::types::Pubkey;
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+// How the server answers for one account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockResponse {
+    // Account data, served base64-encoded
+    Account(Vec<u8>),
+    // `result: null`, as for an account that doesn't exist
+    Missing,
+    // A JSON-RPC error object
+    RpcError { code: i32, message: String },
+    // A bare HTTP error status
+    HttpStatus(u16),
+    // A 200 response with this body, JSON or not
+    Malformed(String),
+    // Another response, after a pause. In a getMultipleAccounts call the
+    // pauses add up, and an error or malformed response for any account
+    // fails the whole call.
+    Delayed(Duration, Box<MockResponse>),
+}
+
+#[derive(Default)]
+struct MockState {
+    responses: HashMap<String, MockResponse>,
+    // Method of every JSON-RPC call received, in order
+    requests: Vec<String>,
+}
+
+pub struct MockRpcServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockRpcServer {
+    // Bind to an ephemeral localhost port and start serving
+    pub fn start() -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let state = Arc::clone(&state);
+            let shutdown = Arc::clone(&shutdown);
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::Acquire) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        let state = Arc::clone(&state);
+                        std::thread::spawn(move || handle_connection(stream, &state));
+                    }
+                }
+            })
+        };
+
+        Ok(Self {
+            addr,
+            state,
+            shutdown,
+            handle: Some(handle),
+        })
+    }
+
+    // URL to use as `pyth_cluster_url`
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    // Set the response for an account key. Unknown keys get `Missing`.
+    pub fn set_response(&self, account_key: &Pubkey, response: MockResponse) {
+        if let Ok(mut state) = self.state.lock() {
+            state.responses.insert(account_key.to_base58(), response);
+        }
+    }
+
+    pub fn set_account(&self, account_key: &Pubkey, data: Vec<u8>) {
+        self.set_response(account_key, MockResponse::Account(data));
+    }
+
+    // Methods of the JSON-RPC calls received so far
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().map(|state| state.requests.clone()).unwrap_or_default()
+    }
+}
+
+impl Drop for MockRpcServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Release);
+        // Wake the accept loop so it sees the flag
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, state: &Mutex<MockState>) {
+    let body = match read_request_body(&stream) {
+        Some(body) => body,
+        None => return,
+    };
+
+    let (status, body) = match serde_json::from_slice::<Value>(&body) {
+        Ok(request) => respond(&request, state),
+        Err(_) => (400, "Bad request".to_string()),
+    };
+
+    let reason = if status == 200 { "OK" } else { "Error" };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+fn read_request_body(stream: &TcpStream) -> Option<Vec<u8>> {
+    let mut reader = BufReader::new(stream);
+    let mut content_length = 0;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().ok()?;
+            }
+        }
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).ok()?;
+    Some(body)
+}
+
+fn respond(request: &Value, state: &Mutex<MockState>) -> (u16, String) {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request.get("method").and_then(Value::as_str).unwrap_or_default().to_string();
+
+    let mut state = match state.lock() {
+        Ok(state) => state,
+        Err(_) => return (500, "Mock state poisoned".to_string()),
+    };
+    state.requests.push(method.clone());
+    let lookup = |key: &Value| {
+        key.as_str()
+            .and_then(|key| state.responses.get(key).cloned())
+            .unwrap_or(MockResponse::Missing)
+    };
+
+    match method.as_str() {
+        "getAccountInfo" => {
+            let response = lookup(&request["params"][0]);
+            drop(state);
+            render(response, id)
+        }
+        "getMultipleAccounts" => {
+            let keys = request["params"][0].as_array().cloned().unwrap_or_default();
+            let responses: Vec<MockResponse> = keys.iter().map(lookup).collect();
+            drop(state);
+            render_multiple(responses, id)
+        }
+        _ => {
+            drop(state);
+            let response = MockResponse::RpcError {
+                code: -32601,
+                message: "Method not found".to_string(),
+            };
+            render(response, id)
+        }
+    }
+}
+
+fn account_value(data: &[u8]) -> Value {
+    json!({
+        "data": [general_purpose::STANDARD.encode(data), "base64"],
+        "executable": false,
+        "lamports": 1_000_000,
+        "owner": "FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH",
+        "rentEpoch": 0,
+    })
+}
+
+fn render_multiple(responses: Vec<MockResponse>, id: Value) -> (u16, String) {
+    let mut values = Vec::new();
+    for mut response in responses {
+        while let MockResponse::Delayed(delay, inner) = response {
+            std::thread::sleep(delay);
+            response = *inner;
+        }
+        match response {
+            MockResponse::Account(data) => values.push(account_value(&data)),
+            MockResponse::Missing => values.push(Value::Null),
+            failure => return render(failure, id),
+        }
+    }
+
+    let result = json!({"context": {"slot": 1}, "value": values});
+    (200, json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string())
+}
+
+fn render(response: MockResponse, id: Value) -> (u16, String) {
+    match response {
+        MockResponse::Account(data) => {
+            let result = json!({"context": {"slot": 1}, "value": account_value(&data)});
+            (200, json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string())
+        }
+        MockResponse::Missing => {
+            let result = json!({"context": {"slot": 1}, "value": null});
+            (200, json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string())
+        }
+        MockResponse::RpcError { code, message } => {
+            let error = json!({"code": code, "message": message});
+            (200, json!({"jsonrpc": "2.0", "id": id, "error": error}).to_string())
+        }
+        MockResponse::HttpStatus(status) => (status, String::new()),
+        MockResponse::Malformed(body) => (200, body),
+        MockResponse::Delayed(delay, response) => {
+            std::thread::sleep(delay);
+            render(*response, id)
+        }
+    }
+}
+
+// Bytes of a legacy Pyth v2 price account with the given price
+pub fn price_account_data(price: i64, conf: u64, expo: i32, publish_time: i64) -> Vec<u8> {
+    let mut data = vec![0u8; MIN_PRICE_ACCOUNT_SIZE];
+    data[0..4].copy_from_slice(&PYTH_MAGIC_NUMBER.to_le_bytes());
+    data[4..8].copy_from_slice(&PYTH_VERSION_V2.to_le_bytes());
+    data[8..12].copy_from_slice(&PYTH_ACCOUNT_TYPE_PRICE.to_le_bytes());
+    data[12..16].copy_from_slice(&(MIN_PRICE_ACCOUNT_SIZE as u32).to_le_bytes());
+    data[EXPO_OFFSET..EXPO_OFFSET + 4].copy_from_slice(&expo.to_le_bytes());
+    data[TIMESTAMP_OFFSET..TIMESTAMP_OFFSET + 8].copy_from_slice(&publish_time.to_le_bytes());
+    data[PRICE_OFFSET..PRICE_OFFSET + 8].copy_from_slice(&price.to_le_bytes());
+    data[CONF_OFFSET..CONF_OFFSET + 8].copy_from_slice(&conf.to_le_bytes());
+    data
+}
@@ -1,5 +1,8 @@
-use crate::types::*;
+use crate::lookup_tables::{self, LoadedAccountKeys};
+use crate::owned::{Bundle, Instruction};
+use crate::types::Pubkey;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[repr(C)]
@@ -64,9 +67,14 @@ pub struct PythPriceInfo {
     pub pub_slot: u64,
 }
 
+// Prices are cached by Pyth feed id. A legacy push-oracle price account is
+// its own feed id; any other price account (a pull-oracle `PriceUpdateV2`)
+// maps to the feed id found in its data when it was fetched. Lookups accept
+// either a feed id or a price account key.
 #[derive(Debug, Clone)]
 pub struct OracleCache {
     pub prices: lru::LruCache<[u8; 32], PriceData>,
+    pub feed_ids: HashMap<[u8; 32], [u8; 32]>,
     pub last_update: SystemTime,
     pub update_count: u64,
 }
@@ -75,6 +83,7 @@ impl Default for OracleCache {
     fn default() -> Self {
         Self {
             prices: lru::LruCache::new(std::num::NonZeroUsize::new(1000).unwrap()),
+            feed_ids: HashMap::new(),
             last_update: UNIX_EPOCH,
             update_count: 0,
         }
@@ -82,8 +91,28 @@ impl Default for OracleCache {
 }
 
 impl OracleCache {
+    // Feed id for a price account key, or the id itself if it isn't a
+    // mapped account
+    pub fn resolve_price_id(&self, price_id: &[u8; 32]) -> [u8; 32] {
+        self.feed_ids.get(price_id).copied().unwrap_or(*price_id)
+    }
+
+    pub fn map_price_account(&mut self, price_account: [u8; 32], feed_id: [u8; 32]) {
+        if price_account == feed_id {
+            self.feed_ids.remove(&price_account);
+        } else {
+            self.feed_ids.insert(price_account, feed_id);
+        }
+    }
+
     pub fn get_price(&mut self, price_id: &[u8; 32]) -> Option<&PriceData> {
-        self.prices.get(price_id)
+        let feed_id = self.resolve_price_id(price_id);
+        self.prices.get(&feed_id)
+    }
+
+    // Like `get_price`, without touching the LRU order
+    pub fn peek_price(&self, price_id: &[u8; 32]) -> Option<&PriceData> {
+        self.prices.peek(&self.resolve_price_id(price_id))
     }
 
     pub fn update_price(&mut self, price_id: [u8; 32], price_data: PriceData) {
@@ -106,14 +135,46 @@ impl OracleCache {
     }
 }
 
+// Also the `oracle` section of the plugin config; fields left out keep
+// their defaults
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct OracleConfig {
     pub pyth_cluster_url: String,
-    pub price_account_keys: Vec<String>,
+    pub price_account_keys: Vec<Pubkey>,
     pub max_price_age_seconds: u64,
     pub update_interval_ms: u64,
     pub verification_level: u8,
     pub enable_just_in_time_updates: bool,
+    // Per-request RPC timeout
+    pub request_timeout_ms: u64,
+    // How long a bundle may wait on a just-in-time fetch of stale prices
+    pub jit_fetch_timeout_ms: u64,
+    // Wormhole guardian set that signs pull-oracle accumulator updates, as
+    // hex addresses. Updates are refused while it's empty.
+    pub wormhole_guardian_set_index: u32,
+    pub wormhole_guardians: Vec<String>,
+}
+
+impl OracleConfig {
+    // Checked when the client is initialized, so a bad setting fails there
+    // instead of on every fetch
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.pyth_cluster_url.is_empty() {
+            return Err("Oracle cluster URL is empty");
+        }
+        if self.update_interval_ms == 0 {
+            return Err("Oracle update interval must be positive");
+        }
+        if self.request_timeout_ms == 0 {
+            return Err("Oracle request timeout must be positive");
+        }
+        if self.jit_fetch_timeout_ms == 0 {
+            return Err("Oracle JIT fetch timeout must be positive");
+        }
+        crate::pyth_pull::parse_guardian_addresses(&self.wormhole_guardians)?;
+        Ok(())
+    }
 }
 
 impl Default for OracleConfig {
@@ -121,14 +182,18 @@ impl Default for OracleConfig {
         Self {
             pyth_cluster_url: "https://api.mainnet-beta.solana.com".to_string(),
             price_account_keys: vec![
-                "GVXRSBjFk6e6J3NbVPXohDJetcTjaeeuykUpbQF8UoMU".to_string(), // BTC/USD
-                "H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG".to_string(), // ETH/USD
-                "Gnt27xtC473ZT2Mw5u8wZ68Z3gULkSTb5DuxJy7eJotD".to_string(), // SOL/USD
+                Pubkey::from_base58("GVXRSBjFk6e6J3NbVPXohDJetcTjaeeuykUpbQF8UoMU").unwrap(), // BTC/USD
+                Pubkey::from_base58("H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG").unwrap(), // ETH/USD
+                Pubkey::from_base58("Gnt27xtC473ZT2Mw5u8wZ68Z3gULkSTb5DuxJy7eJotD").unwrap(), // SOL/USD
             ],
             max_price_age_seconds: 30,
             update_interval_ms: 1000,
             verification_level: 2,
             enable_just_in_time_updates: true,
+            request_timeout_ms: 5000,
+            jit_fetch_timeout_ms: 250,
+            wormhole_guardian_set_index: 0,
+            wormhole_guardians: Vec::new(),
         }
     }
 }
@@ -151,39 +216,28 @@ pub struct PriceInjectionPoint {
     pub required_price_id: [u8; 32],
 }
 
-pub fn extract_price_injection_points(bundle: &TransactionBundle) -> Vec<PriceInjectionPoint> {
+pub fn extract_price_injection_points(bundle: &Bundle) -> Vec<PriceInjectionPoint> {
     let mut injection_points = Vec::new();
-    
-    if bundle.transactions.is_null() || bundle.transaction_count == 0 {
-        return injection_points;
-    }
-
-    unsafe {
-        let transactions = std::slice::from_raw_parts(
-            bundle.transactions,
-            bundle.transaction_count as usize,
-        );
 
-        for (tx_idx, transaction) in transactions.iter().enumerate() {
-            if transaction.message.instructions.is_null() {
+    for (tx_idx, transaction) in bundle.transactions.iter().enumerate() {
+        // v0 messages may reference price accounts through lookup tables
+        let account_keys = match lookup_tables::resolve_account_keys(transaction) {
+            Ok(keys) => keys,
+            Err(e) => {
+                log::debug!("Skipping injection scan for tx {}: {}", tx_idx, e);
                 continue;
             }
-
-            let instructions = std::slice::from_raw_parts(
-                transaction.message.instructions,
-                transaction.message.instructions_count as usize,
-            );
-
-            for (inst_idx, instruction) in instructions.iter().enumerate() {
-                if is_price_update_instruction(instruction) {
-                    if let Some(price_account) = extract_price_account(instruction, &transaction.message) {
-                        injection_points.push(PriceInjectionPoint {
-                            transaction_index: tx_idx,
-                            instruction_index: inst_idx,
-                            price_account,
-                            required_price_id: derive_price_id_from_account(&price_account),
-                        });
-                    }
+        };
+
+        for (inst_idx, instruction) in transaction.instructions.iter().enumerate() {
+            if is_price_update_instruction(instruction) {
+                if let Some(price_account) = extract_price_account(instruction, &account_keys) {
+                    injection_points.push(PriceInjectionPoint {
+                        transaction_index: tx_idx,
+                        instruction_index: inst_idx,
+                        price_account,
+                        required_price_id: derive_price_id_from_account(&price_account),
+                    });
                 }
             }
         }
@@ -192,13 +246,9 @@ pub fn extract_price_injection_points(bundle: &TransactionBundle) -> Vec<PriceIn
     injection_points
 }
 
-pub unsafe fn is_price_update_instruction(instruction: &CompiledInstruction) -> bool {
-    if instruction.data.is_null() || instruction.data_len < 8 {
-        return false;
-    }
+pub fn is_price_update_instruction(instruction: &Instruction) -> bool {
+    let instruction_data = &instruction.data;
 
-    let instruction_data = std::slice::from_raw_parts(instruction.data, instruction.data_len.into());
-    
     // Check for Pyth program instruction discriminators
     instruction_data.len() >= 8 && (
         instruction_data[0..8] == [0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00] || // update_price
@@ -206,32 +256,17 @@ pub unsafe fn is_price_update_instruction(instruction: &CompiledInstruction) ->
     )
 }
 
-unsafe fn extract_price_account(instruction: &CompiledInstruction, message: &TransactionMessage) -> Option<[u8; 32]> {
-    if instruction.accounts.is_null() || instruction.accounts_count == 0 {
-        return None;
-    }
-
-    if message.account_keys.is_null() || message.account_keys_count == 0 {
-        return None;
-    }
-
-    let account_indices = std::slice::from_raw_parts(instruction.accounts, instruction.accounts_count.into());
-    let account_keys = std::slice::from_raw_parts(message.account_keys, message.account_keys_count as usize);
-
+fn extract_price_account(instruction: &Instruction, account_keys: &LoadedAccountKeys) -> Option<[u8; 32]> {
     // First account is typically the price account for Pyth updates
-    if account_indices.len() > 0 {
-        let account_index = account_indices[0] as usize;
-        if account_index < account_keys.len() {
-            return Some(account_keys[account_index].bytes);
-        }
-    }
-
-    None
+    instruction.accounts
+        .first()
+        .and_then(|&index| account_keys.get(index))
+        .map(|key| key.bytes)
 }
 
+// A legacy price account is its own feed id. Pull-oracle accounts only get
+// theirs once fetched; the oracle pipeline resolves them through the cache.
 fn derive_price_id_from_account(price_account: &[u8; 32]) -> [u8; 32] {
-    // For now, use the account key as the price ID
-    // In a real implementation, this would involve parsing the price account data
     *price_account
 }
 
@@ -264,5 +299,11 @@ pub fn calculate_price_confidence_score(price_data: &PriceData, current_time: i6
         30
     };
 
-    ((age_score + conf_score) / 2).min(100) as u8
+    // A tight confidence interval can't rescue a price that is over a minute old
+    let blended = (age_score + conf_score) / 2;
+    if age_seconds >= 60 {
+        blended.min(age_score).min(100) as u8
+    } else {
+        blended.min(100) as u8
+    }
 }
\ No newline at end of file
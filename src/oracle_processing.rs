@@ -1,6 +1,11 @@
+use crate::compute_budget;
+use crate::owned::{Bundle, Tx};
+use crate::report::{ProcessingReport, Stage};
 use crate::types::*;
 use crate::oracle::*;
-use crate::validation;
+use crate::processing;
+use crate::sandwich;
+use crate::scheduling;
 use crate::fees;
 use crate::PLUGIN_STATE;
 
@@ -18,54 +23,53 @@ static ORACLE_RUNTIME: Lazy<Runtime> = Lazy::new(|| {
         .expect("Failed to create oracle runtime")
 });
 
-pub unsafe fn process_oracle_bundle(bundle: *mut TransactionBundle) -> i32 {
-    let bundle_ref = match bundle.as_ref() {
-        Some(b) => b,
-        None => return ERROR_NULL_POINTER,
-    };
-
-    // First, run standard V1 validation
-    let validation_result = validation::validate_bundle(bundle_ref);
-    if validation_result != SUCCESS {
-        log::error!("Oracle bundle validation failed with code: {}", validation_result);
-        return validation_result;
+pub fn process_oracle_bundle(bundle: &Bundle, report: &mut ProcessingReport) -> i32 {
+    // First, run the same admission stages as V1
+    let admission_result = processing::run_admission_stages(bundle, report);
+    if admission_result != SUCCESS {
+        log::error!("Oracle bundle admission failed with code: {}", admission_result);
+        return admission_result;
     }
 
     // Check oracle feature is enabled
     #[cfg(not(feature = "oracle"))]
     {
         log::warn!("Oracle feature not enabled, falling back to basic processing");
-        return crate::processing::process_bundle(bundle);
+        return processing::complete_bundle(bundle, report);
     }
 
     #[cfg(feature = "oracle")]
     {
-        ORACLE_RUNTIME.block_on(process_oracle_enabled_bundle(bundle_ref))
+        ORACLE_RUNTIME.block_on(process_oracle_enabled_bundle(bundle, report))
     }
 }
 
 #[cfg(feature = "oracle")]
-async fn process_oracle_enabled_bundle(bundle: &TransactionBundle) -> i32 {
+async fn process_oracle_enabled_bundle(bundle: &Bundle, report: &mut ProcessingReport) -> i32 {
     let start_time = SystemTime::now();
 
     // Step 1: Extract price injection points
-    let injection_points = extract_price_injection_points(bundle);
+    let mut injection_points = extract_price_injection_points(bundle);
+    report.price_injection_points = injection_points.len();
     
     if injection_points.is_empty() {
         log::debug!("No oracle price injection points found, using standard processing");
-        return unsafe { crate::processing::process_bundle(bundle as *const _ as *mut _) };
+        return processing::complete_bundle(bundle, report);
     }
 
     log::debug!("Found {} oracle price injection points", injection_points.len());
 
-    // Step 2: Ensure fresh oracle data
-    let fetch_result = pyth_client::fetch_oracle_prices().await;
+    // Step 2: Prices come from the cache the background refresher keeps;
+    // only stale or missing ones are fetched, with a bounded wait
+    report.enter(Stage::OracleFetch);
+    let fetch_result = pyth_client::fetch_stale_prices(&injection_points).await;
     if fetch_result != SUCCESS {
-        log::error!("Failed to fetch oracle prices: {}", fetch_result);
-        return fetch_result;
+        log::warn!("Just-in-time price fetch failed: {}", fetch_result);
     }
 
     // Step 3: Validate we have all required prices
+    report.enter(Stage::OraclePrices);
+    pyth_client::resolve_injection_price_ids(&mut injection_points).await;
     for point in &injection_points {
         match pyth_client::get_oracle_price(&point.required_price_id).await {
             Ok(price_data) => {
@@ -78,76 +82,81 @@ async fn process_oracle_enabled_bundle(bundle: &TransactionBundle) -> i32 {
                 );
 
                 if confidence_score < 30 {
-                    log::error!(
+                    let reason = format!(
                         "Price confidence too low ({}%) for injection at tx:{}, inst:{}",
                         confidence_score,
                         point.transaction_index,
                         point.instruction_index
                     );
-                    return ERROR_ORACLE_STALE_PRICE;
+                    log::error!("{}", reason);
+                    return report.reject(ERROR_ORACLE_STALE_PRICE, Some(point.transaction_index), reason);
                 }
             }
             Err(error_code) => {
-                log::error!(
+                let reason = format!(
                     "Missing required price for injection at tx:{}, inst:{} - error: {}",
                     point.transaction_index,
                     point.instruction_index,
                     error_code
                 );
-                return error_code;
+                log::error!("{}", reason);
+                return report.reject(error_code, Some(point.transaction_index), reason);
             }
         }
     }
 
     // Step 4: Calculate oracle-enhanced fees
-    let base_fee = unsafe { fees::calculate_bundle_fee(bundle as *const _) };
-    let oracle_fee = calculate_oracle_processing_fee(&injection_points);
-    let total_required_fee = base_fee + oracle_fee;
+    report.enter(Stage::FeeCheck);
+    let mut fee_breakdown = fees::calculate_fee_breakdown(bundle);
+    let base_fee = fee_breakdown.bundle_fee;
+    let oracle_fee = fees::calculate_oracle_fee(bundle, injection_points.len());
+    let total_required_fee = base_fee.saturating_add(oracle_fee);
+    fee_breakdown.oracle_fee = oracle_fee;
+    fee_breakdown.required_fee = total_required_fee;
+    report.fees = fee_breakdown;
 
     if bundle.metadata.plugin_fees < total_required_fee {
-        log::error!(
+        let reason = format!(
             "Insufficient fee for oracle processing: {} < {} (base: {}, oracle: {})",
             bundle.metadata.plugin_fees,
             total_required_fee,
             base_fee,
             oracle_fee
         );
-        return ERROR_INSUFFICIENT_FEE;
+        log::error!("{}", reason);
+        return report.reject(ERROR_INSUFFICIENT_FEE, None, reason);
     }
 
     // Step 5: Perform just-in-time price injection
     // Note: In a real implementation, price injection would modify bundle data
     // For now, we simulate the injection process
-    let injection_result = pyth_client::inject_oracle_prices(
-        std::ptr::null_mut(), // Placeholder - real implementation would pass mutable bundle
-        &injection_points,
-    ).await;
+    report.enter(Stage::OracleInjection);
+    let injection_result = pyth_client::inject_oracle_prices(bundle, &injection_points).await;
 
     if injection_result != SUCCESS {
         log::error!("Oracle price injection failed: {}", injection_result);
-        return injection_result;
+        return report.reject(injection_result, None, "Oracle price injection failed");
     }
 
     // Step 6: Apply oracle-aware optimizations
-    let optimization_result = unsafe { apply_oracle_optimizations(bundle, &injection_points) };
+    report.enter(Stage::Optimization);
+    let optimization_result = apply_oracle_optimizations(bundle, &injection_points, report);
     if optimization_result != SUCCESS {
         return optimization_result;
     }
 
-    // Step 7: Update metrics
+    // Step 7: Update oracle metrics; the bundle itself is counted once it
+    // lands
     let processing_time = start_time.elapsed().unwrap_or_default().as_micros() as u64;
     
     if let Ok(mut state) = PLUGIN_STATE.lock() {
-        state.bundles_processed += 1;
-        state.total_fees_collected += bundle.metadata.plugin_fees;
-        
         // Update oracle-specific metrics (if state supports them)
         update_oracle_metrics(&mut state, &injection_points, processing_time);
     }
 
     log::info!(
         "Oracle bundle processed successfully: {} transactions, {} price injections, {}μs",
-        bundle.transaction_count,
+        bundle.transactions.len(),
         injection_points.len(),
         processing_time
     );
@@ -155,41 +164,20 @@ async fn process_oracle_enabled_bundle(bundle: &TransactionBundle) -> i32 {
     SUCCESS
 }
 
-fn calculate_oracle_processing_fee(injection_points: &[PriceInjectionPoint]) -> u64 {
-    // Base oracle fee per injection point
-    const BASE_ORACLE_FEE: u64 = 10_000; // 0.01 SOL per price injection
-    const COMPLEXITY_MULTIPLIER: u64 = 2_000; // Additional fee for complex operations
-
-    let base_total = injection_points.len() as u64 * BASE_ORACLE_FEE;
-    
-    // Add complexity fee for bundles with many injection points
-    let complexity_fee = if injection_points.len() > 5 {
-        (injection_points.len() as u64 - 5) * COMPLEXITY_MULTIPLIER
-    } else {
-        0
-    };
-
-    base_total + complexity_fee
-}
-
-unsafe fn apply_oracle_optimizations(
-    bundle: &TransactionBundle,
+fn apply_oracle_optimizations(
+    bundle: &Bundle,
     injection_points: &[PriceInjectionPoint],
+    report: &mut ProcessingReport,
 ) -> i32 {
-    if bundle.transactions.is_null() || bundle.transaction_count == 0 {
+    if bundle.transactions.is_empty() {
         return SUCCESS;
     }
 
-    let transactions = std::slice::from_raw_parts(
-        bundle.transactions,
-        bundle.transaction_count as usize,
-    );
-
     // Group transactions by oracle dependency
     let mut oracle_dependent_txs = Vec::new();
     let mut independent_txs = Vec::new();
 
-    for (idx, _tx) in transactions.iter().enumerate() {
+    for idx in 0..bundle.transactions.len() {
         let has_oracle_dependency = injection_points.iter()
             .any(|point| point.transaction_index == idx);
 
@@ -213,6 +201,12 @@ unsafe fn apply_oracle_optimizations(
             independent_txs, oracle_dependent_txs);
     }
 
+    report.suggested_order = scheduling::execution_plan(bundle, &oracle_dependent_txs);
+    sandwich::protect_victims(bundle, &mut report.suggested_order, &report.sandwiches);
+    let dropped = &report.dropped_transactions;
+    report.suggested_order.retain(|idx| !dropped.contains(idx));
+    report.oracle_dependent_transactions = oracle_dependent_txs;
+
     // Check for price feed conflicts
     detect_price_feed_conflicts(injection_points);
 
@@ -230,8 +224,8 @@ fn detect_price_feed_conflicts(injection_points: &[PriceInjectionPoint]) {
     for (price_id, usages) in price_usage {
         if usages.len() > 1 {
             log::debug!(
-                "Price feed {:?} used in {} locations: {:?}",
-                hex::encode(price_id),
+                "Price feed {} used in {} locations: {:?}",
+                Pubkey { bytes: price_id },
                 usages.len(),
                 usages
             );
@@ -254,17 +248,8 @@ fn update_oracle_metrics(
 }
 
 // Oracle-aware transaction validation
-pub unsafe fn validate_oracle_transactions(bundle: &TransactionBundle) -> i32 {
-    if bundle.transactions.is_null() || bundle.transaction_count == 0 {
-        return SUCCESS;
-    }
-
-    let transactions = std::slice::from_raw_parts(
-        bundle.transactions,
-        bundle.transaction_count as usize,
-    );
-
-    for (tx_idx, transaction) in transactions.iter().enumerate() {
+pub fn validate_oracle_transactions(bundle: &Bundle) -> i32 {
+    for (tx_idx, transaction) in bundle.transactions.iter().enumerate() {
         // Validate oracle-specific constraints
         if let Err(error_code) = validate_oracle_transaction(transaction, tx_idx) {
             return error_code;
@@ -274,7 +259,7 @@ pub unsafe fn validate_oracle_transactions(bundle: &TransactionBundle) -> i32 {
     SUCCESS
 }
 
-unsafe fn validate_oracle_transaction(transaction: &Transaction, tx_index: usize) -> Result<(), i32> {
+fn validate_oracle_transaction(transaction: &Tx, tx_index: usize) -> Result<(), i32> {
     // Check for excessive oracle dependencies
     let oracle_instruction_count = count_oracle_instructions(transaction);
     
@@ -288,12 +273,13 @@ unsafe fn validate_oracle_transaction(transaction: &Transaction, tx_index: usize
     }
 
     // Validate compute budget for oracle operations
-    let estimated_oracle_compute = oracle_instruction_count * 10_000; // 10k CU per oracle op
-    if transaction.compute_limit < estimated_oracle_compute {
+    let estimated_oracle_compute = oracle_instruction_count as u64 * 10_000; // 10k CU per oracle op
+    let compute_units = compute_budget::compute_units(transaction);
+    if compute_units < estimated_oracle_compute {
         log::warn!(
             "Transaction {} may have insufficient compute for oracle operations: {} < {}",
             tx_index,
-            transaction.compute_limit,
+            compute_units,
             estimated_oracle_compute
         );
     }
@@ -301,51 +287,40 @@ unsafe fn validate_oracle_transaction(transaction: &Transaction, tx_index: usize
     Ok(())
 }
 
-unsafe fn count_oracle_instructions(transaction: &Transaction) -> u32 {
-    if transaction.message.instructions.is_null() {
-        return 0;
-    }
-
-    let instructions = std::slice::from_raw_parts(
-        transaction.message.instructions,
-        transaction.message.instructions_count as usize,
-    );
-
-    instructions.iter()
+fn count_oracle_instructions(transaction: &Tx) -> u32 {
+    transaction.instructions.iter()
         .filter(|inst| crate::oracle::is_price_update_instruction(inst))
         .count() as u32
 }
 
+pub fn estimate_oracle_fee(bundle: &Bundle) -> u64 {
+    let injection_points = extract_price_injection_points(bundle);
+    let base_fee = fees::calculate_bundle_fee(bundle);
+    let oracle_fee = fees::calculate_oracle_fee(bundle, injection_points.len());
+    base_fee.saturating_add(oracle_fee)
+}
+
 // Export oracle-specific FFI functions
 #[no_mangle]
 pub extern "C" fn process_oracle_bundle_ffi(bundle: *mut TransactionBundle) -> i32 {
-    unsafe { process_oracle_bundle(bundle) }
+    match Bundle::from_ffi(bundle).and_then(|bundle| crate::process(&bundle, crate::Pipeline::V2)) {
+        Ok(()) => SUCCESS,
+        Err(code) => code,
+    }
 }
 
 #[no_mangle]
 pub extern "C" fn get_oracle_fee_estimate(bundle: *const TransactionBundle) -> u64 {
-    if bundle.is_null() {
-        return 0;
-    }
-
-    unsafe {
-        let bundle_ref = bundle.as_ref().unwrap();
-        let injection_points = extract_price_injection_points(bundle_ref);
-        let base_fee = fees::calculate_bundle_fee(bundle);
-        let oracle_fee = calculate_oracle_processing_fee(&injection_points);
-        base_fee + oracle_fee
+    match Bundle::from_ffi(bundle) {
+        Ok(bundle) => estimate_oracle_fee(&bundle),
+        Err(_) => 0,
     }
 }
 
 #[no_mangle]
 pub extern "C" fn get_oracle_injection_count(bundle: *const TransactionBundle) -> u32 {
-    if bundle.is_null() {
-        return 0;
-    }
-
-    unsafe {
-        let bundle_ref = bundle.as_ref().unwrap();
-        let injection_points = extract_price_injection_points(bundle_ref);
-        injection_points.len() as u32
+    match Bundle::from_ffi(bundle) {
+        Ok(bundle) => extract_price_injection_points(&bundle).len() as u32,
+        Err(_) => 0,
     }
 }
\ No newline at end of file
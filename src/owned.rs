@@ -0,0 +1,495 @@
+use crate::compute_budget::{self, ComputeBudgetInstruction, COMPUTE_BUDGET_PROGRAM_ID};
+use crate::types::*;
+use crate::wire;
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+// Owned counterparts of the #[repr(C)] bundle types. These hold their own
+// buffers, so they can be built and passed around without raw pointers and
+// lent to the FFI functions through a `BundleView`. All processing works on
+// these types; `Bundle::from_ffi` is the only place host pointers are read.
+
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    pub transactions: Vec<Tx>,
+    pub metadata: BundleMetadata,
+    pub attestation: Option<BundleAttestation>,
+    // Inclusion rules; hosts pass them next to the raw bundle through
+    // `process_bundle_with_options`
+    pub options: BundleOptions,
+}
+
+// How a bundle may land. By default every transaction lands or none does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BundleOptions {
+    // When false, transactions that don't fit are dropped instead of
+    // failing the bundle
+    pub atomic: bool,
+    // Indices of transactions an atomic bundle may lose, like Jito's
+    // reverting transaction hashes
+    pub reverting_transactions: Vec<usize>,
+    // Compute units left in the target block, when the host knows
+    pub remaining_block_compute_units: Option<u64>,
+}
+
+impl Default for BundleOptions {
+    fn default() -> Self {
+        Self {
+            atomic: true,
+            reverting_transactions: Vec::new(),
+            remaining_block_compute_units: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleAttestation {
+    pub version: u32,
+    pub node_id: [u8; 32],
+    pub bundle_hash: [u8; 32],
+    pub timestamp: u64,
+    pub signature: [u8; 64],
+    // `Some` whenever the host attached a report, even an empty one
+    pub tee_report: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tx {
+    pub signatures: Vec<Signature>,
+    pub version: u8,
+    pub header: MessageHeader,
+    pub account_keys: Vec<Pubkey>,
+    pub recent_blockhash: [u8; 32],
+    pub instructions: Vec<Instruction>,
+    pub address_table_lookups: Vec<AddressTableLookup>,
+    pub priority_fee: u64,
+    pub compute_limit: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub program_id_index: u8,
+    pub accounts: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressTableLookup {
+    pub account_key: Pubkey,
+    pub writable_indexes: Vec<u8>,
+    pub readonly_indexes: Vec<u8>,
+}
+
+impl Bundle {
+    pub fn new(metadata: BundleMetadata) -> Self {
+        Self {
+            transactions: Vec::new(),
+            metadata,
+            attestation: None,
+            options: BundleOptions::default(),
+        }
+    }
+
+    pub fn with_transaction(mut self, tx: Tx) -> Self {
+        self.transactions.push(tx);
+        self
+    }
+
+    pub fn with_attestation(mut self, attestation: BundleAttestation) -> Self {
+        self.attestation = Some(attestation);
+        self
+    }
+
+    pub fn with_options(mut self, options: BundleOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    // Copy a bundle handed over by the host. Null bundles map to
+    // ERROR_NULL_POINTER and malformed layouts to ERROR_INVALID_BUNDLE.
+    pub(crate) fn from_ffi(bundle: *const TransactionBundle) -> Result<Self, i32> {
+        let bundle = match unsafe { bundle.as_ref() } {
+            Some(b) => b,
+            None => {
+                log::error!("Received null bundle pointer");
+                return Err(ERROR_NULL_POINTER);
+            }
+        };
+
+        // The host guarantees every pointer is valid for its declared count
+        unsafe { Self::from_raw(bundle) }.map_err(|e| {
+            log::error!("Malformed bundle: {}", e);
+            ERROR_INVALID_BUNDLE
+        })
+    }
+
+    /// Copy a raw bundle into owned buffers.
+    ///
+    /// # Safety
+    /// The bundle's transaction, signature, account and instruction pointers
+    /// must be valid for their declared counts.
+    pub unsafe fn from_raw(bundle: &TransactionBundle) -> Result<Self, &'static str> {
+        let transactions = copy_raw(bundle.transactions, bundle.transaction_count as usize)
+            .ok_or("Null transactions pointer")?;
+
+        Ok(Self {
+            transactions: transactions
+                .iter()
+                .map(|tx| Tx::from_raw(tx))
+                .collect::<Result<Vec<_>, _>>()?,
+            metadata: bundle.metadata,
+            attestation: match bundle.attestation.as_ref() {
+                Some(attestation) => Some(BundleAttestation::from_raw(attestation)?),
+                None => None,
+            },
+            options: BundleOptions::default(),
+        })
+    }
+
+    /// Lend this bundle to the FFI functions as a `TransactionBundle`.
+    ///
+    /// The view's pointers borrow from `self`; the FFI functions only read
+    /// through them. Fails if any list is too long for its raw count field.
+    pub fn view(&self) -> Result<BundleView<'_>, &'static str> {
+        let instructions: Vec<Vec<CompiledInstruction>> = self
+            .transactions
+            .iter()
+            .map(|tx| tx.instructions.iter().map(Instruction::as_compiled).collect())
+            .collect::<Result<_, _>>()?;
+
+        let lookups: Vec<Vec<MessageAddressTableLookup>> = self
+            .transactions
+            .iter()
+            .map(|tx| tx.address_table_lookups.iter().map(AddressTableLookup::as_raw).collect())
+            .collect::<Result<_, _>>()?;
+
+        let transactions: Vec<Transaction> = self
+            .transactions
+            .iter()
+            .zip(instructions.iter().zip(&lookups))
+            .map(|(tx, (compiled, raw_lookups))| {
+                Ok(Transaction {
+                    signatures: tx.signatures.as_ptr() as *mut Signature,
+                    signature_count: raw_count(tx.signatures.len(), "Too many signatures")?,
+                    message: TransactionMessage {
+                        header: tx.header,
+                        account_keys: tx.account_keys.as_ptr() as *mut Pubkey,
+                        account_keys_count: raw_count(tx.account_keys.len(), "Too many account keys")?,
+                        recent_blockhash: tx.recent_blockhash,
+                        instructions: compiled.as_ptr() as *mut CompiledInstruction,
+                        instructions_count: raw_count(compiled.len(), "Too many instructions")?,
+                        version: tx.version,
+                        address_table_lookups: raw_lookups.as_ptr() as *mut MessageAddressTableLookup,
+                        address_table_lookups_count: raw_count(raw_lookups.len(), "Too many address table lookups")?,
+                    },
+                    priority_fee: tx.priority_fee,
+                    compute_limit: tx.compute_limit,
+                })
+            })
+            .collect::<Result<_, &'static str>>()?;
+
+        let attestation = match &self.attestation {
+            Some(a) => Some(Box::new(a.as_raw()?)),
+            None => None,
+        };
+
+        Ok(BundleView {
+            bundle: TransactionBundle {
+                transaction_count: raw_count(transactions.len(), "Too many transactions")?,
+                transactions: transactions.as_ptr() as *mut Transaction,
+                metadata: self.metadata,
+                attestation: attestation
+                    .as_deref()
+                    .map_or(std::ptr::null_mut(), |a| a as *const Attestation as *mut Attestation),
+            },
+            _transactions: transactions,
+            _instructions: instructions,
+            _lookups: lookups,
+            _attestation: attestation,
+            _owner: PhantomData,
+        })
+    }
+}
+
+impl BundleAttestation {
+    /// Copy a raw attestation into owned buffers.
+    ///
+    /// # Safety
+    /// `tee_report` must be null or valid for `tee_report_len` bytes.
+    pub unsafe fn from_raw(attestation: &Attestation) -> Result<Self, &'static str> {
+        let tee_report = if attestation.tee_report.is_null() {
+            None
+        } else {
+            copy_raw(attestation.tee_report, attestation.tee_report_len as usize)
+        };
+
+        Ok(Self {
+            version: attestation.version,
+            node_id: attestation.node_id,
+            bundle_hash: attestation.bundle_hash,
+            timestamp: attestation.timestamp,
+            signature: attestation.signature,
+            tee_report,
+        })
+    }
+
+    fn as_raw(&self) -> Result<Attestation, &'static str> {
+        Ok(Attestation {
+            version: self.version,
+            node_id: self.node_id,
+            bundle_hash: self.bundle_hash,
+            timestamp: self.timestamp,
+            signature: self.signature,
+            tee_report: self
+                .tee_report
+                .as_ref()
+                .map_or(std::ptr::null_mut(), |report| report.as_ptr() as *mut u8),
+            tee_report_len: raw_count(self.tee_report.as_ref().map_or(0, Vec::len), "TEE report too long")?,
+        })
+    }
+}
+
+impl Tx {
+    // Unsigned legacy transaction with the default compute limit
+    pub fn new(header: MessageHeader, recent_blockhash: [u8; 32]) -> Self {
+        Self {
+            signatures: Vec::new(),
+            version: MESSAGE_VERSION_LEGACY,
+            header,
+            account_keys: Vec::new(),
+            recent_blockhash,
+            instructions: Vec::new(),
+            address_table_lookups: Vec::new(),
+            priority_fee: 0,
+            compute_limit: wire::DEFAULT_COMPUTE_LIMIT,
+        }
+    }
+
+    pub fn with_account_key(mut self, key: Pubkey) -> Self {
+        self.account_keys.push(key);
+        self
+    }
+
+    pub fn with_instruction(mut self, instruction: Instruction) -> Self {
+        self.instructions.push(instruction);
+        self
+    }
+
+    // Lookups only exist in versioned messages, so this switches to v0
+    pub fn with_lookup(mut self, lookup: AddressTableLookup) -> Self {
+        self.version = MESSAGE_VERSION_V0;
+        self.address_table_lookups.push(lookup);
+        self
+    }
+
+    pub fn with_priority_fee(mut self, priority_fee: u64) -> Self {
+        self.priority_fee = priority_fee;
+        self
+    }
+
+    pub fn with_compute_limit(mut self, compute_limit: u32) -> Self {
+        self.compute_limit = compute_limit;
+        self
+    }
+
+    // Append a compute budget instruction, adding the program as a readonly
+    // unsigned account if needed, and refresh the compute limit and priority
+    // fee from the budget instructions. Fails when the message has no room
+    // left for the program account.
+    pub fn with_compute_budget(mut self, instruction: ComputeBudgetInstruction) -> Result<Self, &'static str> {
+        let program_index = match self.account_keys.iter().position(|key| *key == COMPUTE_BUDGET_PROGRAM_ID) {
+            Some(index) => index,
+            None => {
+                self.header.num_readonly_unsigned_accounts = self.header.num_readonly_unsigned_accounts
+                    .checked_add(1)
+                    .ok_or("Too many readonly unsigned accounts")?;
+                self.account_keys.push(COMPUTE_BUDGET_PROGRAM_ID);
+                self.account_keys.len() - 1
+            }
+        };
+        let program_index = raw_count(program_index, "Too many account keys")?;
+
+        self.instructions.push(Instruction::new(program_index, Vec::new(), instruction.encode()));
+
+        if let Ok(budget) = compute_budget::parse_compute_budget(&self) {
+            self.priority_fee = budget.priority_fee();
+            self.compute_limit = budget.compute_unit_limit;
+        }
+        Ok(self)
+    }
+
+    // Replace the signatures with ones over the current message; signers are
+    // given in the order of the leading account keys. Fails when the message
+    // can't be encoded.
+    pub fn sign(mut self, signers: &[&SigningKey]) -> Result<Self, &'static str> {
+        let message = self.message_bytes()?;
+        self.signatures = signers
+            .iter()
+            .map(|signer| Signature { bytes: signer.sign(&message).to_bytes() })
+            .collect();
+        Ok(self)
+    }
+
+    // The signed portion of the transaction in wire format
+    pub fn message_bytes(&self) -> Result<Vec<u8>, &'static str> {
+        wire::encode_message(self)
+    }
+
+    // The fee payer's signature, which Solana tooling uses as the
+    // transaction id
+    pub fn signature(&self) -> Option<Signature> {
+        self.signatures.first().copied()
+    }
+
+    pub fn blockhash(&self) -> Blockhash {
+        Blockhash::from(self.recent_blockhash)
+    }
+
+    /// Copy a raw transaction into owned buffers.
+    ///
+    /// # Safety
+    /// The transaction's signature, account and instruction pointers must be
+    /// valid for their declared counts.
+    pub unsafe fn from_raw(tx: &Transaction) -> Result<Self, &'static str> {
+        let message = &tx.message;
+
+        Ok(Self {
+            signatures: copy_raw(tx.signatures, tx.signature_count as usize)
+                .ok_or("Null signatures pointer")?,
+            version: message.version,
+            header: message.header,
+            account_keys: copy_raw(message.account_keys, message.account_keys_count as usize)
+                .ok_or("Null account keys pointer")?,
+            recent_blockhash: message.recent_blockhash,
+            instructions: copy_raw(message.instructions, message.instructions_count as usize)
+                .ok_or("Null instructions pointer")?
+                .iter()
+                .map(|instruction| Instruction::from_raw(instruction))
+                .collect::<Result<Vec<_>, _>>()?,
+            address_table_lookups: copy_raw(
+                message.address_table_lookups,
+                message.address_table_lookups_count as usize,
+            )
+            .ok_or("Null address table lookups pointer")?
+            .iter()
+            .map(|lookup| AddressTableLookup::from_raw(lookup))
+            .collect::<Result<Vec<_>, _>>()?,
+            priority_fee: tx.priority_fee,
+            compute_limit: tx.compute_limit,
+        })
+    }
+}
+
+impl Instruction {
+    pub fn new(program_id_index: u8, accounts: Vec<u8>, data: Vec<u8>) -> Self {
+        Self {
+            program_id_index,
+            accounts,
+            data,
+        }
+    }
+
+    /// Copy a raw instruction into owned buffers.
+    ///
+    /// # Safety
+    /// `accounts` and `data` must be valid for their declared lengths.
+    pub unsafe fn from_raw(instruction: &CompiledInstruction) -> Result<Self, &'static str> {
+        Ok(Self {
+            program_id_index: instruction.program_id_index,
+            accounts: copy_raw(instruction.accounts, instruction.accounts_count as usize)
+                .ok_or("Null instruction accounts pointer")?,
+            data: copy_raw(instruction.data, instruction.data_len as usize)
+                .ok_or("Null instruction data pointer")?,
+        })
+    }
+
+    fn as_compiled(&self) -> Result<CompiledInstruction, &'static str> {
+        Ok(CompiledInstruction {
+            program_id_index: self.program_id_index,
+            accounts: self.accounts.as_ptr() as *mut u8,
+            accounts_count: raw_count(self.accounts.len(), "Too many instruction accounts")?,
+            data: self.data.as_ptr() as *mut u8,
+            data_len: raw_count(self.data.len(), "Instruction data too long")?,
+        })
+    }
+}
+
+impl AddressTableLookup {
+    pub fn new(account_key: Pubkey, writable_indexes: Vec<u8>, readonly_indexes: Vec<u8>) -> Self {
+        Self {
+            account_key,
+            writable_indexes,
+            readonly_indexes,
+        }
+    }
+
+    /// Copy a raw lookup into owned buffers.
+    ///
+    /// # Safety
+    /// The index pointers must be valid for their declared counts.
+    pub unsafe fn from_raw(lookup: &MessageAddressTableLookup) -> Result<Self, &'static str> {
+        Ok(Self {
+            account_key: lookup.account_key,
+            writable_indexes: copy_raw(lookup.writable_indexes, lookup.writable_indexes_count as usize)
+                .ok_or("Null lookup indexes pointer")?,
+            readonly_indexes: copy_raw(lookup.readonly_indexes, lookup.readonly_indexes_count as usize)
+                .ok_or("Null lookup indexes pointer")?,
+        })
+    }
+
+    fn as_raw(&self) -> Result<MessageAddressTableLookup, &'static str> {
+        Ok(MessageAddressTableLookup {
+            account_key: self.account_key,
+            writable_indexes: self.writable_indexes.as_ptr() as *mut u8,
+            writable_indexes_count: raw_count(self.writable_indexes.len(), "Too many lookup indexes")?,
+            readonly_indexes: self.readonly_indexes.as_ptr() as *mut u8,
+            readonly_indexes_count: raw_count(self.readonly_indexes.len(), "Too many lookup indexes")?,
+        })
+    }
+}
+
+// Raw count fields are narrower than `usize`; never truncate into them
+fn raw_count<T: TryFrom<usize>>(len: usize, error: &'static str) -> Result<T, &'static str> {
+    T::try_from(len).map_err(|_| error)
+}
+
+// Empty slices may come with a null pointer; anything else must not
+unsafe fn copy_raw<T: Clone>(ptr: *const T, len: usize) -> Option<Vec<T>> {
+    if len == 0 {
+        return Some(Vec::new());
+    }
+    if ptr.is_null() {
+        return None;
+    }
+    Some(std::slice::from_raw_parts(ptr, len).to_vec())
+}
+
+// A `TransactionBundle` whose pointers borrow from an owned `Bundle`
+pub struct BundleView<'a> {
+    bundle: TransactionBundle,
+    _transactions: Vec<Transaction>,
+    _instructions: Vec<Vec<CompiledInstruction>>,
+    _lookups: Vec<Vec<MessageAddressTableLookup>>,
+    _attestation: Option<Box<Attestation>>,
+    _owner: PhantomData<&'a Bundle>,
+}
+
+impl BundleView<'_> {
+    pub fn as_ptr(&self) -> *const TransactionBundle {
+        &self.bundle
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut TransactionBundle {
+        &mut self.bundle
+    }
+}
+
+impl std::ops::Deref for BundleView<'_> {
+    type Target = TransactionBundle;
+
+    fn deref(&self) -> &TransactionBundle {
+        &self.bundle
+    }
+}
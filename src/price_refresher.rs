@@ -0,0 +1,85 @@
+// Keeps the Pyth price cache warm from a background thread, so bundle
+// processing reads prices from the cache instead of waiting on the RPC node.
+// Each pass refreshes the configured accounts, then sleeps for the client's
+// current `update_interval_ms`; nothing is fetched until the client is
+// initialized. Applying a config with an `oracle` section starts the
+// plugin's refresher and `plugin_shutdown` stops it.
+
+use crate::pyth_client;
+use once_cell::sync::Lazy;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+
+pub struct PriceRefresher {
+    stop: Sender<()>,
+    handle: Option<JoinHandle<()>>,
+}
+
+static REFRESHER: Lazy<Mutex<Option<PriceRefresher>>> = Lazy::new(|| Mutex::new(None));
+
+impl PriceRefresher {
+    pub fn start() -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let (stop, stopped) = mpsc::channel();
+
+        let handle = std::thread::Builder::new()
+            .name("oracle-refresher".to_string())
+            .spawn(move || loop {
+                let interval = runtime.block_on(async {
+                    if pyth_client::is_initialized().await {
+                        pyth_client::fetch_oracle_prices().await;
+                    }
+                    pyth_client::update_interval().await
+                });
+                // A stop request or a dropped sender both end the loop
+                if stopped.recv_timeout(interval) != Err(RecvTimeoutError::Timeout) {
+                    break;
+                }
+            })?;
+
+        Ok(Self {
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.handle.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+}
+
+impl Drop for PriceRefresher {
+    // Waits out a refresh in flight, at most the RPC request timeout
+    fn drop(&mut self) {
+        let _ = self.stop.send(());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// Start the plugin's refresher unless it is already running
+pub fn start_background_refresh() -> std::io::Result<()> {
+    let mut refresher = REFRESHER.lock().unwrap_or_else(|e| e.into_inner());
+    if refresher.as_ref().is_some_and(PriceRefresher::is_running) {
+        return Ok(());
+    }
+
+    *refresher = Some(PriceRefresher::start()?);
+    log::info!("Oracle price refresher started");
+    Ok(())
+}
+
+pub fn stop_background_refresh() {
+    // Joined outside the lock
+    let refresher = REFRESHER.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if refresher.is_some() {
+        drop(refresher);
+        log::info!("Oracle price refresher stopped");
+    }
+}
+
+pub fn is_background_refresh_running() -> bool {
+    REFRESHER.lock().unwrap_or_else(|e| e.into_inner()).as_ref().is_some_and(PriceRefresher::is_running)
+}
@@ -1,96 +1,121 @@
+use crate::owned::{Bundle, Tx};
+use crate::report::{ProcessingReport, Stage};
 use crate::types::*;
+use crate::atomicity;
+use crate::block_budget;
+use crate::compute_budget;
+use crate::conflicts;
+use crate::sandwich;
+use crate::scheduling;
 use crate::validation;
 use crate::fees;
 use crate::PLUGIN_STATE;
 use std::collections::HashMap;
 
-pub unsafe fn process_bundle(bundle: *mut TransactionBundle) -> i32 {
-    // Dereference bundle safely
-    let bundle_ref = match bundle.as_ref() {
-        Some(b) => b,
-        None => return ERROR_NULL_POINTER,
-    };
+pub fn process_bundle(bundle: &Bundle, report: &mut ProcessingReport) -> i32 {
+    let admission_result = run_admission_stages(bundle, report);
+    if admission_result != SUCCESS {
+        return admission_result;
+    }
+
+    complete_bundle(bundle, report)
+}
 
+// Stages every pipeline runs before its own: validation, the size limit,
+// the sandwich policy, atomicity and block limits
+pub fn run_admission_stages(bundle: &Bundle, report: &mut ProcessingReport) -> i32 {
     // Validate bundle structure
-    let validation_result = validation::validate_bundle(bundle_ref);
+    let validation_result = validation::validate_bundle(bundle, report);
     if validation_result != SUCCESS {
         log::error!("Bundle validation failed with code: {}", validation_result);
         return validation_result;
     }
 
     // Check bundle size limits
+    report.enter(Stage::SizeCheck);
     if let Ok(state) = PLUGIN_STATE.lock() {
-        if bundle_ref.transaction_count > state.config.max_bundle_size {
-            log::error!(
+        if bundle.transactions.len() > state.config.max_bundle_size as usize {
+            let reason = format!(
                 "Bundle exceeds max size: {} > {}",
-                bundle_ref.transaction_count,
+                bundle.transactions.len(),
                 state.config.max_bundle_size
             );
-            return ERROR_INVALID_BUNDLE;
+            log::error!("{}", reason);
+            return report.reject(ERROR_INVALID_BUNDLE, None, reason);
         }
     }
 
+    // Refuse, flag or defuse sandwiches per the configured policy
+    let sandwich_result = sandwich::check_bundle(bundle, report);
+    if sandwich_result != SUCCESS {
+        return sandwich_result;
+    }
+
+    // All-or-nothing bundles must fit the block whole
+    let atomicity_result = atomicity::check_bundle(bundle, report);
+    if atomicity_result != SUCCESS {
+        return atomicity_result;
+    }
+
+    // What lands must fit the slot's block alongside earlier bundles
+    block_budget::check_bundle(bundle, report)
+}
+
+// The rest of V1 after admission: the bundle fee and optimizations
+pub fn complete_bundle(bundle: &Bundle, report: &mut ProcessingReport) -> i32 {
     // Calculate and validate fees
-    let required_fee = fees::calculate_bundle_fee(bundle);
-    if bundle_ref.metadata.plugin_fees < required_fee {
-        log::error!(
+    report.enter(Stage::FeeCheck);
+    report.fees = fees::calculate_fee_breakdown(bundle);
+    let required_fee = report.fees.required_fee;
+    if bundle.metadata.plugin_fees < required_fee {
+        let reason = format!(
             "Insufficient plugin fee: {} < {}",
-            bundle_ref.metadata.plugin_fees,
+            bundle.metadata.plugin_fees,
             required_fee
         );
-        return ERROR_INSUFFICIENT_FEE;
+        log::error!("{}", reason);
+        return report.reject(ERROR_INSUFFICIENT_FEE, None, reason);
     }
 
     // Apply optimizations
-    let optimization_result = apply_bundle_optimizations(bundle_ref);
+    report.enter(Stage::Optimization);
+    let optimization_result = apply_bundle_optimizations(bundle, report);
     if optimization_result != SUCCESS {
         return optimization_result;
     }
 
-    // Update state metrics
-    if let Ok(mut state) = PLUGIN_STATE.lock() {
-        state.bundles_processed += 1;
-        state.total_fees_collected += bundle_ref.metadata.plugin_fees;
-    }
-
     log::debug!(
         "Successfully processed bundle with {} transactions",
-        bundle_ref.transaction_count
+        bundle.transactions.len()
     );
 
     SUCCESS
 }
 
-unsafe fn apply_bundle_optimizations(bundle: &TransactionBundle) -> i32 {
+fn apply_bundle_optimizations(bundle: &Bundle, report: &mut ProcessingReport) -> i32 {
     // IMPORTANT: Do NOT modify BAM Node's memory directly!
     // Instead, analyze and suggest optimizations without mutating
     
-    if bundle.transactions.is_null() || bundle.transaction_count == 0 {
+    let transactions = &bundle.transactions;
+    if transactions.is_empty() {
         return SUCCESS;
     }
 
-    let transactions = std::slice::from_raw_parts(
-        bundle.transactions,
-        bundle.transaction_count as usize
-    );
-
     // Calculate optimal ordering without modifying original data
-    let mut indices: Vec<usize> = (0..transactions.len()).collect();
-    indices.sort_by(|&a, &b| {
-        transactions[b].priority_fee.cmp(&transactions[a].priority_fee)
-    });
+    let mut indices = scheduling::execution_plan(bundle, &[]);
+    sandwich::protect_victims(bundle, &mut indices, &report.sandwiches);
+    indices.retain(|idx| !report.dropped_transactions.contains(idx));
     
     // Log the suggested reordering for BAM Node to use
     log::debug!("Suggested transaction order by priority: {:?}", indices);
+    report.suggested_order = indices;
     
     // Calculate optimization metrics without mutation
-    let total_priority_fees: u64 = transactions.iter()
-        .map(|tx| tx.priority_fee)
-        .sum();
+    let total_priority_fees = transactions.iter()
+        .fold(0u64, |total, tx| total.saturating_add(compute_budget::priority_fee(tx)));
     
-    let total_compute_units: u64 = transactions.iter()
-        .map(|tx| tx.compute_limit as u64)
-        .sum();
+    let total_compute_units = transactions.iter()
+        .fold(0u64, |total, tx| total.saturating_add(compute_budget::compute_units(tx)));
     
     log::debug!(
         "Bundle optimization analysis: {} txs, {} total priority fees, {} total CU",
@@ -98,18 +123,20 @@ unsafe fn apply_bundle_optimizations(bundle: &TransactionBundle) -> i32 {
         total_priority_fees,
         total_compute_units
     );
+    report.total_priority_fees = total_priority_fees;
+    report.total_compute_units = total_compute_units;
     
     // Detect potential optimization opportunities
-    analyze_optimization_opportunities(transactions);
+    analyze_optimization_opportunities(transactions, report);
 
     SUCCESS
 }
 
-unsafe fn analyze_optimization_opportunities(transactions: &[Transaction]) {
+fn analyze_optimization_opportunities(transactions: &[Tx], report: &mut ProcessingReport) {
     // Check for duplicate priority fees (could be batched)
     let mut fee_counts = HashMap::new();
     for tx in transactions {
-        *fee_counts.entry(tx.priority_fee).or_insert(0) += 1;
+        *fee_counts.entry(compute_budget::priority_fee(tx)).or_insert(0) += 1;
     }
     
     let duplicates: Vec<_> = fee_counts.iter()
@@ -119,53 +146,59 @@ unsafe fn analyze_optimization_opportunities(transactions: &[Transaction]) {
     if !duplicates.is_empty() {
         log::debug!("Found {} priority fee groups that could be optimized", duplicates.len());
     }
+    report.duplicate_fee_groups = duplicates.len();
     
     // Check for overly high compute limits (could be reduced)
-    let high_compute_txs = transactions.iter()
-        .filter(|tx| tx.compute_limit > 1_000_000)
-        .count();
+    let high_compute_txs: Vec<usize> = transactions.iter()
+        .enumerate()
+        .filter(|(_, tx)| compute_budget::compute_units(tx) > 1_000_000)
+        .map(|(idx, _)| idx)
+        .collect();
     
-    if high_compute_txs > 0 {
-        log::debug!("Found {} transactions with high compute limits", high_compute_txs);
+    if !high_compute_txs.is_empty() {
+        log::debug!("Found {} transactions with high compute limits", high_compute_txs.len());
     }
+    report.high_compute_transactions = high_compute_txs;
 }
 
-pub fn get_bundle_stats(bundle: &TransactionBundle) -> BundleStats {
+pub fn get_bundle_stats(bundle: &Bundle) -> BundleStats {
+    let analysis = conflicts::analyze_bundle(bundle);
+
     let mut stats = BundleStats {
         total_compute_units: 0,
         total_priority_fees: 0,
         unique_programs: 0,
         max_accounts_per_tx: 0,
+        write_write_conflicts: analysis.write_write_conflicts.len() as u32,
+        read_write_conflicts: analysis.read_write_conflicts.len() as u32,
+        parallel_batches: analysis.parallel_batches.len() as u32,
+        max_parallel_transactions: analysis.parallel_batches.iter()
+            .map(|batch| batch.len() as u32)
+            .max()
+            .unwrap_or(0),
     };
 
-    if bundle.transactions.is_null() {
-        return stats;
-    }
-
-    unsafe {
-        let transactions = std::slice::from_raw_parts(
-            bundle.transactions,
-            bundle.transaction_count as usize
-        );
-
-        for tx in transactions {
-            stats.total_compute_units += tx.compute_limit as u64;
-            stats.total_priority_fees += tx.priority_fee;
-            
-            if !tx.message.account_keys.is_null() {
-                stats.max_accounts_per_tx = stats.max_accounts_per_tx
-                    .max(tx.message.account_keys_count as u32);
-            }
-        }
+    for tx in &bundle.transactions {
+        stats.total_compute_units = stats.total_compute_units
+            .saturating_add(compute_budget::compute_units(tx));
+        stats.total_priority_fees = stats.total_priority_fees
+            .saturating_add(compute_budget::priority_fee(tx));
+        stats.max_accounts_per_tx = stats.max_accounts_per_tx
+            .max(tx.account_keys.len() as u32);
     }
 
     stats
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct BundleStats {
     pub total_compute_units: u64,
     pub total_priority_fees: u64,
     pub unique_programs: u32,
     pub max_accounts_per_tx: u32,
+    // Per conflicting account, per transaction pair
+    pub write_write_conflicts: u32,
+    pub read_write_conflicts: u32,
+    pub parallel_batches: u32,
+    pub max_parallel_transactions: u32,
 }
\ No newline at end of file
@@ -1,8 +1,10 @@
 use crate::oracle::*;
+use crate::owned::Bundle;
+use crate::pyth_pull::{self, GuardianSet, VerificationLevel};
 use crate::types::*;
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Mutex, RwLock};
 use once_cell::sync::Lazy;
 use base64::{Engine as _, engine::general_purpose};
 
@@ -18,10 +20,24 @@ pub const CONF_OFFSET: usize = 216;
 pub const EXPO_OFFSET: usize = 20;
 pub const TIMESTAMP_OFFSET: usize = 96;
 
+// Most accounts a single getMultipleAccounts call may ask for
+pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
 static PYTH_CLIENT: Lazy<RwLock<PythClient>> = Lazy::new(|| {
     RwLock::new(PythClient::new())
 });
 
+// Held for the length of a full refresh
+static REFRESH_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+// What initializing a client replaces; the price cache stays
+#[derive(Debug, Clone)]
+pub struct PythSetup {
+    config: OracleConfig,
+    http_client: Option<reqwest::Client>,
+    last_fetch_time: SystemTime,
+}
+
 #[derive(Debug, Clone)]
 pub struct PythClient {
     pub config: OracleConfig,
@@ -33,10 +49,8 @@ pub struct PythClient {
 
 #[derive(Debug, Deserialize)]
 struct SolanaRpcResponse<T> {
-    jsonrpc: String,
     result: Option<T>,
     error: Option<SolanaRpcError>,
-    id: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -45,14 +59,16 @@ struct SolanaRpcError {
     message: String,
 }
 
+// Account RPCs wrap their result in a context object; an account that
+// doesn't exist is null
+#[derive(Debug, Deserialize)]
+struct RpcResponseValue<T> {
+    value: Option<T>,
+}
+
 #[derive(Debug, Deserialize)]
 struct AccountInfo {
     data: Vec<String>,
-    executable: bool,
-    lamports: u64,
-    owner: String,
-    #[serde(rename = "rentEpoch")]
-    rent_epoch: u64,
 }
 
 #[derive(Debug, Serialize)]
@@ -63,78 +79,119 @@ struct RpcRequest {
     params: Vec<serde_json::Value>,
 }
 
-impl PythClient {
-    pub fn new() -> Self {
-        Self {
-            config: OracleConfig::default(),
-            cache: OracleCache::default(),
-            http_client: None,
-            last_fetch_time: UNIX_EPOCH,
-            fetch_count: 0,
-        }
+// Outcome of one `fetch_all_prices` call
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PriceFetchReport {
+    // RPC calls made
+    pub requests: usize,
+    // Accounts parsed into the cache
+    pub fetched: usize,
+    pub failed: Vec<PriceFetchError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceFetchError {
+    pub account_key: Pubkey,
+    pub error: String,
+}
+
+impl PriceFetchReport {
+    fn fail(&mut self, account_key: Pubkey, error: String) {
+        log::warn!("Failed to fetch price account {}: {}", account_key, error);
+        self.failed.push(PriceFetchError {
+            account_key,
+            error,
+        });
     }
+}
 
-    pub fn initialize(&mut self, config: OracleConfig) -> Result<(), Box<dyn std::error::Error>> {
-        self.config = config;
-        self.http_client = Some(
-            reqwest::Client::builder()
-                .timeout(std::time::Duration::from_secs(5))
-                .build()?
-        );
-        log::info!("Pyth client initialized with {} price accounts", self.config.price_account_keys.len());
-        Ok(())
+fn decode_account_data(account: Option<AccountInfo>) -> Result<Vec<u8>, String> {
+    let account_info = account.ok_or("Account not found")?;
+    if account_info.data.len() >= 2 && account_info.data[1] == "base64" {
+        general_purpose::STANDARD.decode(&account_info.data[0])
+            .map_err(|e| format!("Base64 decode error: {}", e))
+    } else {
+        Err("Invalid account data encoding".to_string())
     }
+}
 
-    pub async fn fetch_all_prices(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let client = self.http_client.as_ref()
-            .ok_or("HTTP client not initialized")?;
+// The RPC side of a price fetch, detached from the client so the round
+// trips don't hold the client lock
+#[derive(Debug, Clone)]
+pub struct PriceFetch {
+    http_client: reqwest::Client,
+    cluster_url: String,
+    verification_level: u8,
+    pub account_keys: Vec<Pubkey>,
+}
 
-        let mut requests = Vec::new();
-        
-        for (i, account_key) in self.config.price_account_keys.iter().enumerate() {
-            let request = RpcRequest {
-                jsonrpc: "2.0".to_string(),
-                id: i as u64,
-                method: "getAccountInfo".to_string(),
-                params: vec![
-                    serde_json::Value::String(account_key.clone()),
-                    serde_json::json!({
-                        "encoding": "base64",
-                        "commitment": "confirmed"
-                    })
-                ],
-            };
-            requests.push(request);
-        }
+#[derive(Debug, Clone)]
+pub struct FetchedPrice {
+    pub price_account: [u8; 32],
+    pub feed_id: [u8; 32],
+    pub price_data: PriceData,
+}
 
-        // Batch fetch all accounts
-        for request in requests {
-            match self.fetch_price_account(client, request).await {
-                Ok((price_id, price_data)) => {
-                    self.cache.update_price(price_id, price_data);
-                }
+impl PriceFetch {
+    pub async fn run(&self) -> (PriceFetchReport, Vec<FetchedPrice>) {
+        let mut report = PriceFetchReport::default();
+        let mut prices = Vec::new();
+
+        for (i, chunk) in self.account_keys.chunks(MAX_MULTIPLE_ACCOUNTS).enumerate() {
+            report.requests += 1;
+
+            let accounts = match self.fetch_price_accounts(i as u64, chunk).await {
+                Ok(accounts) => accounts,
                 Err(e) => {
-                    log::warn!("Failed to fetch price account: {}", e);
+                    // The whole call failed, so every account in it did
+                    for &account_key in chunk {
+                        report.fail(account_key, e.to_string());
+                    }
+                    continue;
+                }
+            };
+
+            for (&account_key, account) in chunk.iter().zip(accounts) {
+                let parsed = account.and_then(|data| {
+                    let (feed_id, price_data) = self.parse_pyth_price_account(&account_key.bytes, &data)
+                        .map_err(|e| e.to_string())?;
+                    Ok(FetchedPrice { price_account: account_key.bytes, feed_id, price_data })
+                });
+                match parsed {
+                    Ok(fetched) => {
+                        prices.push(fetched);
+                        report.fetched += 1;
+                    }
+                    Err(e) => report.fail(account_key, e),
                 }
             }
         }
 
-        self.last_fetch_time = SystemTime::now();
-        self.fetch_count += 1;
-
-        log::debug!("Fetched {} price accounts (total fetches: {})", 
-            self.config.price_account_keys.len(), self.fetch_count);
-
-        Ok(())
+        (report, prices)
     }
 
-    async fn fetch_price_account(
+    // One `getMultipleAccounts` call. The outer error covers the call; the
+    // inner results are per account, in request order.
+    async fn fetch_price_accounts(
         &self,
-        client: &reqwest::Client,
-        request: RpcRequest,
-    ) -> Result<([u8; 32], PriceData), Box<dyn std::error::Error + Send + Sync>> {
-        let response = client
-            .post(&self.config.pyth_cluster_url)
+        id: u64,
+        account_keys: &[Pubkey],
+    ) -> Result<Vec<Result<Vec<u8>, String>>, Box<dyn std::error::Error + Send + Sync>> {
+        let request = RpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id,
+            method: "getMultipleAccounts".to_string(),
+            params: vec![
+                serde_json::json!(account_keys),
+                serde_json::json!({
+                    "encoding": "base64",
+                    "commitment": "confirmed"
+                })
+            ],
+        };
+
+        let response = self.http_client
+            .post(&self.cluster_url)
             .json(&request)
             .send()
             .await?;
@@ -143,29 +200,40 @@ impl PythClient {
             return Err(format!("HTTP error: {}", response.status()).into());
         }
 
-        let rpc_response: SolanaRpcResponse<AccountInfo> = response.json().await?;
+        let rpc_response: SolanaRpcResponse<RpcResponseValue<Vec<Option<AccountInfo>>>> = response.json().await?;
 
         if let Some(error) = rpc_response.error {
-            return Err(format!("RPC error: {}", error.message).into());
+            return Err(format!("RPC error {}: {}", error.code, error.message).into());
         }
 
-        let account_info = rpc_response.result
+        let accounts = rpc_response.result
+            .and_then(|result| result.value)
             .ok_or("No account data returned")?;
 
-        let account_data = if account_info.data.len() >= 2 && account_info.data[1] == "base64" {
-            general_purpose::STANDARD.decode(&account_info.data[0])
-                .map_err(|e| format!("Base64 decode error: {}", e))?
-        } else {
-            return Err("Invalid account data encoding".into());
-        };
+        if accounts.len() != account_keys.len() {
+            return Err(format!("Expected {} accounts, got {}", account_keys.len(), accounts.len()).into());
+        }
 
-        self.parse_pyth_price_account(&account_data)
+        Ok(accounts.into_iter().map(decode_account_data).collect())
     }
 
+    // Parse a fetched price account into its feed id and price
     fn parse_pyth_price_account(
         &self,
+        price_account: &[u8; 32],
         data: &[u8],
     ) -> Result<([u8; 32], PriceData), Box<dyn std::error::Error + Send + Sync>> {
+        // Pull-oracle accounts carry their feed id
+        if pyth_pull::is_price_update_v2(data) {
+            let update = pyth_pull::parse_price_update_v2(data)?;
+            if self.verification_level >= VERIFICATION_LEVEL_STRICT
+                && update.verification_level != VerificationLevel::Full
+            {
+                return Err("Price update is only partially verified".into());
+            }
+            return Ok((update.price_message.feed_id, update.price_message.price_data()));
+        }
+
         if data.len() < MIN_PRICE_ACCOUNT_SIZE {
             return Err("Account data too short for Pyth price account".into());
         }
@@ -215,10 +283,6 @@ impl PythClient {
             data[timestamp_offset + 4], data[timestamp_offset + 5], data[timestamp_offset + 6], data[timestamp_offset + 7],
         ]);
 
-        // Generate price ID from account key (simplified)
-        let mut price_id = [0u8; 32];
-        price_id[..8].copy_from_slice(&data[32..40]); // Use part of product account as ID
-
         let price_data = PriceData {
             price,
             conf,
@@ -226,7 +290,130 @@ impl PythClient {
             publish_time: timestamp,
         };
 
-        Ok((price_id, price_data))
+        // Push-oracle price accounts are their own feed id
+        Ok((*price_account, price_data))
+    }
+}
+
+impl Default for PythClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PythClient {
+    pub fn new() -> Self {
+        Self {
+            config: OracleConfig::default(),
+            cache: OracleCache::default(),
+            http_client: None,
+            last_fetch_time: UNIX_EPOCH,
+            fetch_count: 0,
+        }
+    }
+
+    pub fn initialize(&mut self, config: OracleConfig) -> Result<(), Box<dyn std::error::Error>> {
+        config.validate()?;
+        let http_client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_millis(config.request_timeout_ms))
+            // Fetches run on the refresher's runtime and on the oracle
+            // runtime; a connection pooled by one stalls when the other picks
+            // it up, so none are kept
+            .pool_max_idle_per_host(0)
+            .build()?;
+
+        // New accounts or endpoint: fetch on the next call
+        self.restore(PythSetup {
+            config,
+            http_client: Some(http_client),
+            last_fetch_time: UNIX_EPOCH,
+        });
+        log::info!("Pyth client initialized with {} price accounts", self.config.price_account_keys.len());
+        Ok(())
+    }
+
+    // The config and connection in use, to put back with `restore`
+    pub fn setup(&self) -> PythSetup {
+        PythSetup {
+            config: self.config.clone(),
+            http_client: self.http_client.clone(),
+            last_fetch_time: self.last_fetch_time,
+        }
+    }
+
+    pub fn restore(&mut self, setup: PythSetup) {
+        self.config = setup.config;
+        self.http_client = setup.http_client;
+        self.last_fetch_time = setup.last_fetch_time;
+    }
+
+    // Refresh every configured account, `MAX_MULTIPLE_ACCOUNTS` per
+    // `getMultipleAccounts` call. Failures are reported per account and
+    // leave the cached price in place; only a missing HTTP client is an error.
+    pub async fn fetch_all_prices(&mut self) -> Result<PriceFetchReport, Box<dyn std::error::Error + Send + Sync>> {
+        let fetch = self.price_fetch(self.config.price_account_keys.clone())?;
+        let (report, prices) = fetch.run().await;
+        self.store_prices(prices);
+
+        self.last_fetch_time = SystemTime::now();
+        self.fetch_count += 1;
+
+        log::debug!("Fetched {}/{} price accounts in {} requests (total fetches: {})",
+            report.fetched, fetch.account_keys.len(), report.requests, self.fetch_count);
+
+        Ok(report)
+    }
+
+    // A fetch of `account_keys` that can run without the client
+    pub fn price_fetch(&self, account_keys: Vec<Pubkey>) -> Result<PriceFetch, Box<dyn std::error::Error + Send + Sync>> {
+        let http_client = self.http_client.clone()
+            .ok_or("HTTP client not initialized")?;
+
+        Ok(PriceFetch {
+            http_client,
+            cluster_url: self.config.pyth_cluster_url.clone(),
+            verification_level: self.config.verification_level,
+            account_keys,
+        })
+    }
+
+    // Cache fetched prices under their feed ids. Fetches can overlap, so a
+    // price older than the cached one is skipped.
+    pub fn store_prices(&mut self, prices: Vec<FetchedPrice>) {
+        for fetched in prices {
+            self.cache.map_price_account(fetched.price_account, fetched.feed_id);
+            let newer = self.cache.prices.peek(&fetched.feed_id)
+                .is_none_or(|cached| cached.publish_time <= fetched.price_data.publish_time);
+            if newer {
+                self.cache.update_price(fetched.feed_id, fetched.price_data);
+            }
+        }
+    }
+
+    pub fn guardian_set(&self) -> Result<GuardianSet, &'static str> {
+        Ok(GuardianSet {
+            index: self.config.wormhole_guardian_set_index,
+            addresses: pyth_pull::parse_guardian_addresses(&self.config.wormhole_guardians)?,
+        })
+    }
+
+    // Verify a Hermes accumulator update against the configured guardian set
+    // and cache its prices by feed id. A price older than the cached one is
+    // skipped. Returns the number of prices cached.
+    pub fn apply_accumulator_update(&mut self, data: &[u8]) -> Result<usize, String> {
+        let guardians = self.guardian_set()?;
+        let messages = pyth_pull::verify_accumulator_update(data, &guardians)?;
+
+        let mut applied = 0;
+        for message in messages {
+            let newer = self.cache.prices.peek(&message.feed_id)
+                .is_none_or(|cached| cached.publish_time <= message.publish_time);
+            if newer {
+                self.cache.update_price(message.feed_id, message.price_data());
+                applied += 1;
+            }
+        }
+        Ok(applied)
     }
 
     pub fn get_cached_price(&mut self, price_id: &[u8; 32]) -> Option<PriceData> {
@@ -271,21 +458,197 @@ pub async fn initialize_pyth_client(config: OracleConfig) -> i32 {
     }
 }
 
+// Write access to the client from synchronous code. `blocking_write` panics
+// on a thread running async code, so the lock is taken on a scoped thread
+// and the caller waits for it, whether or not it is inside a runtime.
+fn with_client_blocking<R: Send>(f: impl FnOnce(&mut PythClient) -> R + Send) -> R {
+    std::thread::scope(|scope| {
+        scope.spawn(|| f(&mut PYTH_CLIENT.blocking_write()))
+            .join()
+            .unwrap_or_else(|e| std::panic::resume_unwind(e))
+    })
+}
+
+// `initialize_pyth_client` for synchronous callers such as `plugin_init`.
+// Blocks on the client lock and returns the setup it replaced, for
+// `restore_pyth_client` if a later step fails.
+pub fn configure_pyth_client(config: OracleConfig) -> Result<PythSetup, String> {
+    with_client_blocking(|client| {
+        let previous = client.setup();
+        client.initialize(config).map_err(|e| e.to_string())?;
+        Ok(previous)
+    })
+}
+
+pub fn restore_pyth_client(setup: PythSetup) {
+    with_client_blocking(|client| client.restore(setup))
+}
+
+// Fresh cached price without waiting on the client lock. Returns None while
+// a fetch holds the lock, so synchronous callers never block on the network.
+pub fn peek_cached_price(price_id: &[u8; 32]) -> Option<PriceData> {
+    let client = PYTH_CLIENT.try_read().ok()?;
+    client.cache.peek_price(price_id)
+        .filter(|price_data| !client.is_price_stale(price_data))
+        .cloned()
+}
+
+// Everything in the price cache, by feed id, for traces. Empty while a
+// fetch holds the client lock.
+pub fn cached_prices() -> Vec<([u8; 32], PriceData)> {
+    match PYTH_CLIENT.try_read() {
+        Ok(client) => client.cache.prices.iter().map(|(id, price)| (*id, price.clone())).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Price accounts cached under a different feed id, for traces
+pub fn cached_feed_ids() -> Vec<([u8; 32], [u8; 32])> {
+    match PYTH_CLIENT.try_read() {
+        Ok(client) => client.cache.feed_ids.iter().map(|(account, feed_id)| (*account, *feed_id)).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Replace the cache with recorded prices and mark them as just fetched, so
+// the pipeline uses them instead of going to the network. Blocks on the
+// client lock.
+pub fn restore_cached_prices(prices: Vec<([u8; 32], PriceData)>, feed_ids: Vec<([u8; 32], [u8; 32])>) {
+    with_client_blocking(|client| {
+        client.cache.prices.clear();
+        client.cache.feed_ids.clear();
+        for (price_id, price_data) in prices {
+            client.cache.update_price(price_id, price_data);
+        }
+        for (price_account, feed_id) in feed_ids {
+            client.cache.map_price_account(price_account, feed_id);
+        }
+        client.last_fetch_time = SystemTime::now();
+    })
+}
+
+// The whole price cache and when it was last filled, to put back with
+// `restore_cache`
+pub fn saved_cache() -> (OracleCache, SystemTime) {
+    with_client_blocking(|client| (client.cache.clone(), client.last_fetch_time))
+}
+
+pub fn restore_cache((cache, last_fetch_time): (OracleCache, SystemTime)) {
+    with_client_blocking(|client| {
+        client.cache = cache;
+        client.last_fetch_time = last_fetch_time;
+    })
+}
+
+// Point injection points at the feed ids their price accounts were fetched
+// under, so prices and injections agree on identity
+pub async fn resolve_injection_price_ids(injection_points: &mut [PriceInjectionPoint]) {
+    let client = PYTH_CLIENT.read().await;
+    for point in injection_points {
+        point.required_price_id = client.cache.resolve_price_id(&point.price_account);
+    }
+}
+
+// Refresh every configured account if `update_interval_ms` has passed since
+// the last refresh. The RPC calls run without the client lock, so bundles
+// keep reading the cache meanwhile; concurrent callers wait for the refresh
+// in flight rather than starting another.
 pub async fn fetch_oracle_prices() -> i32 {
+    let _refresh = REFRESH_LOCK.lock().await;
+    if !PYTH_CLIENT.read().await.should_refresh() {
+        return SUCCESS;
+    }
+
+    let fetch = {
+        let mut client = PYTH_CLIENT.write().await;
+        if !client.should_refresh() {
+            return SUCCESS; // No need to refresh
+        }
+        match client.price_fetch(client.config.price_account_keys.clone()) {
+            Ok(fetch) => {
+                // Stamped up front, so re-initializing mid-fetch still forces
+                // the next refresh
+                client.last_fetch_time = SystemTime::now();
+                fetch
+            }
+            Err(e) => {
+                log::error!("Failed to fetch oracle prices: {}", e);
+                return ERROR_ORACLE_NETWORK_FAILURE;
+            }
+        }
+    };
+
+    let (report, prices) = fetch.run().await;
     let mut client = PYTH_CLIENT.write().await;
-    
-    if !client.should_refresh() {
-        return SUCCESS; // No need to refresh
+    client.store_prices(prices);
+    client.fetch_count += 1;
+    log::debug!("Fetched {}/{} price accounts in {} requests",
+        report.fetched, fetch.account_keys.len(), report.requests);
+    SUCCESS
+}
+
+// Just-in-time fetch of the price accounts behind injection points whose
+// price is missing or stale, bounded by `jit_fetch_timeout_ms`. A no-op
+// unless `enable_just_in_time_updates` is set. Failed accounts are left for
+// the price checks to reject.
+pub async fn fetch_stale_prices(injection_points: &[PriceInjectionPoint]) -> i32 {
+    let (fetch, timeout) = {
+        let client = PYTH_CLIENT.read().await;
+        if !client.config.enable_just_in_time_updates {
+            return SUCCESS;
+        }
+
+        let mut accounts: Vec<[u8; 32]> = injection_points.iter()
+            .map(|point| point.price_account)
+            .filter(|account| client.cache.peek_price(account).is_none_or(|price| client.is_price_stale(price)))
+            .collect();
+        accounts.sort_unstable();
+        accounts.dedup();
+        if accounts.is_empty() {
+            return SUCCESS;
+        }
+
+        let account_keys = accounts.into_iter().map(|bytes| Pubkey { bytes }).collect();
+        match client.price_fetch(account_keys) {
+            Ok(fetch) => (fetch, Duration::from_millis(client.config.jit_fetch_timeout_ms)),
+            Err(e) => {
+                log::warn!("Skipping just-in-time price fetch: {}", e);
+                return ERROR_ORACLE_NETWORK_FAILURE;
+            }
+        }
+    };
+
+    match tokio::time::timeout(timeout, fetch.run()).await {
+        Ok((report, prices)) => {
+            PYTH_CLIENT.write().await.store_prices(prices);
+            log::debug!("Fetched {}/{} stale price accounts just in time",
+                report.fetched, fetch.account_keys.len());
+            SUCCESS
+        }
+        Err(_) => {
+            log::warn!("Just-in-time price fetch timed out after {}ms", timeout.as_millis());
+            ERROR_ORACLE_NETWORK_FAILURE
+        }
     }
+}
 
-    match client.fetch_all_prices().await {
-        Ok(_) => {
-            log::debug!("Oracle prices fetched successfully");
+pub async fn is_initialized() -> bool {
+    PYTH_CLIENT.read().await.http_client.is_some()
+}
+
+pub async fn update_interval() -> Duration {
+    Duration::from_millis(PYTH_CLIENT.read().await.config.update_interval_ms)
+}
+
+pub async fn apply_accumulator_update(data: &[u8]) -> i32 {
+    match PYTH_CLIENT.write().await.apply_accumulator_update(data) {
+        Ok(applied) => {
+            log::debug!("Applied {} pull-oracle prices", applied);
             SUCCESS
         }
         Err(e) => {
-            log::error!("Failed to fetch oracle prices: {}", e);
-            ERROR_ORACLE_NETWORK_FAILURE
+            log::error!("Rejected accumulator update: {}", e);
+            ERROR_ORACLE_PARSE_FAILURE
         }
     }
 }
@@ -296,21 +659,21 @@ pub async fn get_oracle_price(price_id: &[u8; 32]) -> Result<PriceData, i32> {
     match client.get_cached_price(price_id) {
         Some(price_data) => {
             if client.is_price_stale(&price_data) {
-                log::warn!("Price data is stale for price_id: {:?}", hex::encode(price_id));
+                log::warn!("Price data is stale for price_id: {}", Pubkey { bytes: *price_id });
                 Err(ERROR_ORACLE_STALE_PRICE)
             } else {
                 Ok(price_data)
             }
         }
         None => {
-            log::warn!("Price not found in cache for price_id: {:?}", hex::encode(price_id));
+            log::warn!("Price not found in cache for price_id: {}", Pubkey { bytes: *price_id });
             Err(ERROR_ORACLE_CACHE_MISS)
         }
     }
 }
 
 pub async fn inject_oracle_prices(
-    _bundle: *mut TransactionBundle,
+    _bundle: &Bundle,
     injection_points: &[PriceInjectionPoint],
 ) -> i32 {
     if injection_points.is_empty() {
@@ -0,0 +1,459 @@
+use crate::oracle::PriceData;
+use k256::ecdsa::{RecoveryId, Signature as EcdsaSignature, VerifyingKey};
+use sha3::{Digest, Keccak256};
+
+// Pyth pull oracle formats: `PriceUpdateV2` accounts posted by the Solana
+// receiver program, and the accumulator updates Hermes serves, where each
+// price message carries a Merkle proof against a root signed by the Wormhole
+// guardians. Feed ids are Pyth's 32-byte price feed ids.
+//
+// Account fields are Borsh (little-endian); accumulator updates, VAAs and
+// the messages inside them are big-endian.
+
+// sha256("account:PriceUpdateV2")[..8]
+pub const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+// Discriminator, write authority, partial verification level, price message
+// and posted slot
+pub const PRICE_UPDATE_V2_SIZE: usize = 8 + 32 + 2 + 84 + 8;
+
+pub const ACCUMULATOR_MAGIC: [u8; 4] = *b"PNAU";
+pub const ACCUMULATOR_MAJOR_VERSION: u8 = 1;
+pub const WORMHOLE_MERKLE_MAGIC: [u8; 4] = *b"AUWV";
+pub const UPDATE_TYPE_WORMHOLE_MERKLE: u8 = 0;
+pub const MESSAGE_TYPE_PRICE_FEED: u8 = 0;
+
+// Pythnet and its accumulator emitter, the only source of signed roots
+pub const PYTHNET_CHAIN_ID: u16 = 26;
+pub const PYTHNET_ACCUMULATOR_EMITTER: [u8; 32] = [
+    0xe1, 0x01, 0xfa, 0xed, 0xac, 0x58, 0x51, 0xe3, 0x2b, 0x9b, 0x23, 0xb5, 0xf9, 0x41, 0x1a, 0x8c,
+    0x2b, 0xac, 0x4a, 0xae, 0x3e, 0xd4, 0xdd, 0x7b, 0x81, 0x1d, 0xd1, 0xa7, 0x2e, 0xa4, 0xaa, 0x71,
+];
+
+const MERKLE_LEAF_PREFIX: u8 = 0;
+const MERKLE_NODE_PREFIX: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub prev_publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+impl PriceFeedMessage {
+    pub fn price_data(&self) -> PriceData {
+        PriceData {
+            price: self.price,
+            conf: self.conf,
+            expo: self.exponent,
+            publish_time: self.publish_time,
+        }
+    }
+}
+
+// How many guardian signatures the receiver checked before posting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationLevel {
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceUpdateV2 {
+    pub write_authority: [u8; 32],
+    pub verification_level: VerificationLevel,
+    pub price_message: PriceFeedMessage,
+    pub posted_slot: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuardianSignature {
+    pub guardian_index: u8,
+    // r, s and the recovery id
+    pub signature: [u8; 65],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vaa {
+    pub version: u8,
+    pub guardian_set_index: u32,
+    pub signatures: Vec<GuardianSignature>,
+    // Everything after the signatures; the guardians sign its double keccak
+    pub body: Vec<u8>,
+    pub timestamp: u32,
+    pub nonce: u32,
+    pub emitter_chain: u16,
+    pub emitter_address: [u8; 32],
+    pub sequence: u64,
+    pub consistency_level: u8,
+    pub payload: Vec<u8>,
+}
+
+// The Merkle root a Pythnet slot's price messages hash up to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WormholeMerkleRoot {
+    pub slot: u64,
+    pub ring_size: u32,
+    pub root: [u8; 20],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerklePriceUpdate {
+    pub message: Vec<u8>,
+    pub proof: Vec<[u8; 20]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccumulatorUpdate {
+    pub major_version: u8,
+    pub minor_version: u8,
+    pub vaa: Vec<u8>,
+    pub updates: Vec<MerklePriceUpdate>,
+}
+
+// Wormhole guardian addresses: the last 20 bytes of the keccak of each
+// guardian's uncompressed secp256k1 key
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GuardianSet {
+    pub index: u32,
+    pub addresses: Vec<[u8; 20]>,
+}
+
+// Guardian addresses as configured: hex, with or without a 0x prefix
+pub fn parse_guardian_addresses(addresses: &[String]) -> Result<Vec<[u8; 20]>, &'static str> {
+    addresses.iter()
+        .map(|address| {
+            hex::decode(address.trim_start_matches("0x"))
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or("Invalid guardian address")
+        })
+        .collect()
+}
+
+impl GuardianSet {
+    // Signatures needed out of the set: more than two thirds
+    pub fn quorum(&self) -> usize {
+        self.addresses.len() * 2 / 3 + 1
+    }
+}
+
+// =========================================================================
+// Parsing
+// =========================================================================
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        let end = self.offset.checked_add(len).filter(|&end| end <= self.data.len()).ok_or("Unexpected end of data")?;
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], &'static str> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8, &'static str> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16_be(&mut self) -> Result<u16, &'static str> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    fn u32_be(&mut self) -> Result<u32, &'static str> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    fn u64_be(&mut self) -> Result<u64, &'static str> {
+        Ok(u64::from_be_bytes(self.array()?))
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.offset..];
+        self.offset = self.data.len();
+        rest
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset == self.data.len()
+    }
+}
+
+pub fn is_price_update_v2(data: &[u8]) -> bool {
+    data.starts_with(&PRICE_UPDATE_V2_DISCRIMINATOR)
+}
+
+pub fn parse_price_update_v2(data: &[u8]) -> Result<PriceUpdateV2, &'static str> {
+    if !is_price_update_v2(data) {
+        return Err("Not a PriceUpdateV2 account");
+    }
+
+    let mut reader = Reader::new(&data[8..]);
+    let write_authority = reader.array()?;
+    let verification_level = match reader.u8()? {
+        0 => VerificationLevel::Partial { num_signatures: reader.u8()? },
+        1 => VerificationLevel::Full,
+        _ => return Err("Invalid verification level"),
+    };
+
+    let price_message = PriceFeedMessage {
+        feed_id: reader.array()?,
+        price: i64::from_le_bytes(reader.array()?),
+        conf: u64::from_le_bytes(reader.array()?),
+        exponent: i32::from_le_bytes(reader.array()?),
+        publish_time: i64::from_le_bytes(reader.array()?),
+        prev_publish_time: i64::from_le_bytes(reader.array()?),
+        ema_price: i64::from_le_bytes(reader.array()?),
+        ema_conf: u64::from_le_bytes(reader.array()?),
+    };
+    let posted_slot = u64::from_le_bytes(reader.array()?);
+
+    Ok(PriceUpdateV2 {
+        write_authority,
+        verification_level,
+        price_message,
+        posted_slot,
+    })
+}
+
+pub fn parse_price_feed_message(data: &[u8]) -> Result<PriceFeedMessage, &'static str> {
+    let mut reader = Reader::new(data);
+    if reader.u8()? != MESSAGE_TYPE_PRICE_FEED {
+        return Err("Not a price feed message");
+    }
+
+    // Newer message versions may append fields
+    Ok(PriceFeedMessage {
+        feed_id: reader.array()?,
+        price: reader.u64_be()? as i64,
+        conf: reader.u64_be()?,
+        exponent: reader.u32_be()? as i32,
+        publish_time: reader.u64_be()? as i64,
+        prev_publish_time: reader.u64_be()? as i64,
+        ema_price: reader.u64_be()? as i64,
+        ema_conf: reader.u64_be()?,
+    })
+}
+
+pub fn parse_accumulator_update(data: &[u8]) -> Result<AccumulatorUpdate, &'static str> {
+    let mut reader = Reader::new(data);
+    if reader.array::<4>()? != ACCUMULATOR_MAGIC {
+        return Err("Invalid accumulator update magic");
+    }
+
+    let major_version = reader.u8()?;
+    if major_version != ACCUMULATOR_MAJOR_VERSION {
+        return Err("Unsupported accumulator update version");
+    }
+    let minor_version = reader.u8()?;
+
+    // Room for header fields added in later minor versions
+    let trailing_header_len = reader.u8()? as usize;
+    reader.bytes(trailing_header_len)?;
+
+    if reader.u8()? != UPDATE_TYPE_WORMHOLE_MERKLE {
+        return Err("Unsupported accumulator update type");
+    }
+
+    let vaa_len = reader.u16_be()? as usize;
+    let vaa = reader.bytes(vaa_len)?.to_vec();
+
+    let num_updates = reader.u8()?;
+    let mut updates = Vec::with_capacity(num_updates as usize);
+    for _ in 0..num_updates {
+        let message_len = reader.u16_be()? as usize;
+        let message = reader.bytes(message_len)?.to_vec();
+        let proof_len = reader.u8()?;
+        let proof = (0..proof_len).map(|_| reader.array()).collect::<Result<Vec<_>, _>>()?;
+        updates.push(MerklePriceUpdate { message, proof });
+    }
+
+    if !reader.is_empty() {
+        return Err("Trailing bytes after accumulator update");
+    }
+
+    Ok(AccumulatorUpdate {
+        major_version,
+        minor_version,
+        vaa,
+        updates,
+    })
+}
+
+pub fn parse_vaa(data: &[u8]) -> Result<Vaa, &'static str> {
+    let mut reader = Reader::new(data);
+    let version = reader.u8()?;
+    if version != 1 {
+        return Err("Unsupported VAA version");
+    }
+    let guardian_set_index = reader.u32_be()?;
+
+    let num_signatures = reader.u8()?;
+    let mut signatures = Vec::with_capacity(num_signatures as usize);
+    for _ in 0..num_signatures {
+        signatures.push(GuardianSignature {
+            guardian_index: reader.u8()?,
+            signature: reader.array()?,
+        });
+    }
+
+    let body = &data[reader.offset..];
+    let timestamp = reader.u32_be()?;
+    let nonce = reader.u32_be()?;
+    let emitter_chain = reader.u16_be()?;
+    let emitter_address = reader.array()?;
+    let sequence = reader.u64_be()?;
+    let consistency_level = reader.u8()?;
+    let payload = reader.rest().to_vec();
+
+    Ok(Vaa {
+        version,
+        guardian_set_index,
+        signatures,
+        body: body.to_vec(),
+        timestamp,
+        nonce,
+        emitter_chain,
+        emitter_address,
+        sequence,
+        consistency_level,
+        payload,
+    })
+}
+
+pub fn parse_wormhole_merkle_root(payload: &[u8]) -> Result<WormholeMerkleRoot, &'static str> {
+    let mut reader = Reader::new(payload);
+    if reader.array::<4>()? != WORMHOLE_MERKLE_MAGIC {
+        return Err("Invalid Wormhole Merkle payload magic");
+    }
+    if reader.u8()? != UPDATE_TYPE_WORMHOLE_MERKLE {
+        return Err("Unsupported Wormhole payload type");
+    }
+
+    Ok(WormholeMerkleRoot {
+        slot: reader.u64_be()?,
+        ring_size: reader.u32_be()?,
+        root: reader.array()?,
+    })
+}
+
+// =========================================================================
+// Verification
+// =========================================================================
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+// Pyth's Merkle trees use keccak truncated to 160 bits, with prefixes that
+// keep leaves and nodes apart and sibling pairs hashed in sorted order
+fn keccak160(parts: &[&[u8]]) -> [u8; 20] {
+    let mut hasher = Keccak256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut hash = [0u8; 20];
+    hash.copy_from_slice(&hasher.finalize()[..20]);
+    hash
+}
+
+pub fn merkle_leaf_hash(message: &[u8]) -> [u8; 20] {
+    keccak160(&[&[MERKLE_LEAF_PREFIX], message])
+}
+
+pub fn merkle_node_hash(a: &[u8; 20], b: &[u8; 20]) -> [u8; 20] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    keccak160(&[&[MERKLE_NODE_PREFIX], left, right])
+}
+
+pub fn verify_merkle_proof(root: &[u8; 20], message: &[u8], proof: &[[u8; 20]]) -> bool {
+    let hash = proof.iter().fold(merkle_leaf_hash(message), |hash, sibling| merkle_node_hash(&hash, sibling));
+    &hash == root
+}
+
+// What guardians sign: the keccak of the body's keccak
+pub fn vaa_digest(body: &[u8]) -> [u8; 32] {
+    keccak256(&keccak256(body))
+}
+
+pub fn guardian_address(key: &VerifyingKey) -> [u8; 20] {
+    let point = key.to_encoded_point(false);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&keccak256(&point.as_bytes()[1..])[12..]);
+    address
+}
+
+// Check a quorum of distinct guardians from `guardians` signed the VAA.
+// Signatures must come in increasing guardian order, as Wormhole requires.
+pub fn verify_vaa(vaa: &Vaa, guardians: &GuardianSet) -> Result<(), &'static str> {
+    if guardians.addresses.is_empty() {
+        return Err("No guardian set configured");
+    }
+    if vaa.guardian_set_index != guardians.index {
+        return Err("VAA signed by a different guardian set");
+    }
+    if vaa.signatures.len() < guardians.quorum() {
+        return Err("Not enough guardian signatures");
+    }
+
+    let digest = vaa_digest(&vaa.body);
+    let mut last_index = None;
+    for signature in &vaa.signatures {
+        if last_index.is_some_and(|last| signature.guardian_index <= last) {
+            return Err("Guardian signatures out of order");
+        }
+        last_index = Some(signature.guardian_index);
+
+        let expected = guardians.addresses.get(signature.guardian_index as usize)
+            .ok_or("Guardian index out of range")?;
+        let ecdsa = EcdsaSignature::from_slice(&signature.signature[..64]).map_err(|_| "Malformed guardian signature")?;
+        let recovery_id = RecoveryId::from_byte(signature.signature[64]).ok_or("Malformed guardian signature")?;
+        let key = VerifyingKey::recover_from_prehash(&digest, &ecdsa, recovery_id)
+            .map_err(|_| "Invalid guardian signature")?;
+        if &guardian_address(&key) != expected {
+            return Err("Invalid guardian signature");
+        }
+    }
+
+    Ok(())
+}
+
+// Verify an accumulator update end to end: guardian signatures on the VAA,
+// the VAA's Pythnet origin, then each message's proof against the signed
+// root. Returns the price feed messages in update order.
+pub fn verify_accumulator_update(data: &[u8], guardians: &GuardianSet) -> Result<Vec<PriceFeedMessage>, &'static str> {
+    let update = parse_accumulator_update(data)?;
+    let vaa = parse_vaa(&update.vaa)?;
+    verify_vaa(&vaa, guardians)?;
+
+    if vaa.emitter_chain != PYTHNET_CHAIN_ID || vaa.emitter_address != PYTHNET_ACCUMULATOR_EMITTER {
+        return Err("VAA not emitted by the Pythnet accumulator");
+    }
+    let root = parse_wormhole_merkle_root(&vaa.payload)?;
+
+    update.updates.iter()
+        .map(|update| {
+            if !verify_merkle_proof(&root.root, &update.message, &update.proof) {
+                return Err("Invalid Merkle proof");
+            }
+            parse_price_feed_message(&update.message)
+        })
+        .collect()
+}
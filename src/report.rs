@@ -0,0 +1,142 @@
+use crate::block_budget::SimulatedBlock;
+use crate::fees::FeeBreakdown;
+use crate::sandwich::Sandwich;
+use crate::types::*;
+use crate::Pipeline;
+use serde::{Deserialize, Serialize};
+
+// Everything a pipeline worked out about a bundle, for callers that need more
+// than the bare result code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessingReport {
+    pub pipeline: Pipeline,
+    pub result: i32,
+    // Stages in the order they ran; the last one decided a rejection
+    pub stages: Vec<Stage>,
+    pub rejection: Option<Rejection>,
+    pub transaction_count: usize,
+    pub total_compute_units: u64,
+    pub total_priority_fees: u64,
+    pub suggested_order: Vec<usize>,
+    pub duplicate_fee_groups: usize,
+    pub high_compute_transactions: Vec<usize>,
+    pub fees: FeeBreakdown,
+    pub price_injection_points: usize,
+    pub oracle_dependent_transactions: Vec<usize>,
+    pub market_maker_transactions: Vec<usize>,
+    pub arbitrage_opportunities: Vec<ArbitrageOpportunity>,
+    pub sandwiches: Vec<Sandwich>,
+    // Transactions that land under the bundle's options, and those dropped
+    // because they didn't fit the block
+    pub included_transactions: Vec<usize>,
+    pub dropped_transactions: Vec<usize>,
+    pub processing_time_us: u64,
+    // Block to check against in place of the slot's live one
+    #[serde(skip)]
+    pub(crate) block: Option<SimulatedBlock>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Conversion,
+    Validation,
+    SizeCheck,
+    SandwichCheck,
+    Atomicity,
+    BlockLimits,
+    FeeCheck,
+    Optimization,
+    OracleFetch,
+    OraclePrices,
+    OracleInjection,
+    MarketMakerPriority,
+    Compliance,
+    RiskLimits,
+    ArbitrageDetection,
+    // Block assembly, after the pipeline has accepted a bundle
+    BlockPacking,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rejection {
+    pub stage: Stage,
+    pub code: i32,
+    pub transaction_index: Option<usize>,
+    pub reason: String,
+}
+
+impl Rejection {
+    pub fn new(stage: Stage, code: i32, transaction_index: Option<usize>, reason: impl Into<String>) -> Self {
+        Self {
+            stage,
+            code,
+            transaction_index,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl ProcessingReport {
+    pub fn new(pipeline: Pipeline) -> Self {
+        Self {
+            pipeline,
+            result: SUCCESS,
+            stages: Vec::new(),
+            rejection: None,
+            transaction_count: 0,
+            total_compute_units: 0,
+            total_priority_fees: 0,
+            suggested_order: Vec::new(),
+            duplicate_fee_groups: 0,
+            high_compute_transactions: Vec::new(),
+            fees: FeeBreakdown::default(),
+            price_injection_points: 0,
+            oracle_dependent_transactions: Vec::new(),
+            market_maker_transactions: Vec::new(),
+            arbitrage_opportunities: Vec::new(),
+            sandwiches: Vec::new(),
+            included_transactions: Vec::new(),
+            dropped_transactions: Vec::new(),
+            processing_time_us: 0,
+            block: None,
+        }
+    }
+
+    // Report for a host bundle that never made it past conversion
+    pub(crate) fn conversion_failure(pipeline: Pipeline, code: i32) -> Self {
+        let mut report = Self::new(pipeline);
+        report.enter(Stage::Conversion);
+        let reason = if code == ERROR_NULL_POINTER {
+            "Null bundle pointer"
+        } else {
+            "Malformed bundle"
+        };
+        report.reject(code, None, reason);
+        report
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.result == SUCCESS
+    }
+
+    pub(crate) fn enter(&mut self, stage: Stage) {
+        self.stages.push(stage);
+    }
+
+    // Record why the current stage rejected the bundle and pass the code on
+    pub(crate) fn reject(&mut self, code: i32, transaction_index: Option<usize>, reason: impl Into<String>) -> i32 {
+        let stage = self.stages.last().copied().unwrap_or(Stage::Conversion);
+        self.record(Rejection::new(stage, code, transaction_index, reason))
+    }
+
+    pub(crate) fn record(&mut self, rejection: Rejection) -> i32 {
+        let code = rejection.code;
+        // Nested pipelines pass failures up; the innermost reason wins
+        if self.rejection.is_none() {
+            self.rejection = Some(rejection);
+        }
+        self.result = code;
+        code
+    }
+}
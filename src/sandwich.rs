@@ -0,0 +1,201 @@
+use crate::conflicts;
+use crate::mev;
+use crate::owned::Bundle;
+use crate::report::{ProcessingReport, Stage};
+use crate::types::*;
+use crate::PLUGIN_STATE;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// What to do with a bundle that sandwiches a third party's swap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SandwichPolicy {
+    // Refuse the bundle with ERROR_SANDWICH_DETECTED
+    #[default]
+    Reject,
+    // Accept it, but list the sandwiches in the report
+    Flag,
+    // Accept it with each victim scheduled ahead of its front-run, so the
+    // attacker's legs only trade against each other. A victim that waits on
+    // something which itself waits on the front-run can't move, and its
+    // bundle is refused as under Reject.
+    Reorder,
+}
+
+// One signer trading a pool before and after someone else's swap on it.
+// Indices are transaction indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sandwich {
+    pub front_run: usize,
+    pub victim: usize,
+    pub back_run: usize,
+    pub pool: Pubkey,
+    pub attacker: Pubkey,
+}
+
+// A front-run and back-run by one signer on one pool, in opposite directions,
+// with a different signer's swap on that pool between them trading the same
+// way as the front-run. Directions the instructions don't reveal count as
+// matching.
+pub fn detect_sandwiches(bundle: &Bundle) -> Vec<Sandwich> {
+    let swaps = mev::detect_swaps(bundle);
+    let mut seen = HashSet::new();
+    let mut sandwiches = Vec::new();
+
+    for front in &swaps {
+        let back_runs = swaps.iter().filter(|back| {
+            back.transaction_index > front.transaction_index
+                && back.signer == front.signer
+                && back.pool == front.pool
+                && front.same_direction(back) != Some(true)
+        });
+
+        for back in back_runs {
+            let victims = swaps.iter().filter(|victim| {
+                victim.transaction_index > front.transaction_index
+                    && victim.transaction_index < back.transaction_index
+                    && victim.signer != front.signer
+                    && victim.pool == front.pool
+                    && front.same_direction(victim) != Some(false)
+            });
+
+            for victim in victims {
+                let key = (front.transaction_index, victim.transaction_index, back.transaction_index);
+                if seen.insert(key) {
+                    sandwiches.push(Sandwich {
+                        front_run: front.transaction_index,
+                        victim: victim.transaction_index,
+                        back_run: back.transaction_index,
+                        pool: front.pool,
+                        attacker: front.signer,
+                    });
+                }
+            }
+        }
+    }
+
+    sandwiches
+}
+
+pub fn configured_policy() -> SandwichPolicy {
+    match PLUGIN_STATE.lock() {
+        Ok(state) => state.config.sandwich_policy,
+        Err(_) => SandwichPolicy::default(),
+    }
+}
+
+// Look for sandwiches and apply the configured policy. Found sandwiches go
+// into the report whatever the policy.
+pub fn check_bundle(bundle: &Bundle, report: &mut ProcessingReport) -> i32 {
+    report.enter(Stage::SandwichCheck);
+
+    let sandwiches = detect_sandwiches(bundle);
+    report.sandwiches = sandwiches.clone();
+    let first = match sandwiches.first() {
+        Some(sandwich) => *sandwich,
+        None => return SUCCESS,
+    };
+
+    match configured_policy() {
+        SandwichPolicy::Reject => {
+            let reason = format!(
+                "Transaction {} is sandwiched by transactions {} and {}",
+                first.victim, first.front_run, first.back_run
+            );
+            log::error!("{}", reason);
+            report.reject(ERROR_SANDWICH_DETECTED, Some(first.victim), reason)
+        }
+        SandwichPolicy::Reorder => {
+            let dependencies = conflicts::analyze_bundle(bundle).dependencies;
+            if let Some(blocked) = blocked_sandwiches(&sandwiches, &dependencies).first() {
+                let reason = format!(
+                    "Transaction {} is sandwiched by transactions {} and {} and can't run ahead of {} without breaking its account dependencies",
+                    blocked.victim, blocked.front_run, blocked.back_run, blocked.front_run
+                );
+                log::error!("{}", reason);
+                return report.reject(ERROR_SANDWICH_DETECTED, Some(blocked.victim), reason);
+            }
+            log::warn!("Reordering {} sandwich pattern(s)", sandwiches.len());
+            SUCCESS
+        }
+        SandwichPolicy::Flag => {
+            log::warn!("Bundle contains {} sandwich pattern(s)", sandwiches.len());
+            SUCCESS
+        }
+    }
+}
+
+fn victims_of(front_run: usize, sandwiches: &[Sandwich]) -> Vec<usize> {
+    sandwiches.iter()
+        .filter(|sandwich| sandwich.front_run == front_run)
+        .map(|sandwich| sandwich.victim)
+        .collect()
+}
+
+// Per transaction, whether it waits on `front_run` other than directly as
+// one of its victims. `dependencies` is the conflict analysis's, which only
+// points at earlier transactions.
+fn waits_on_front_run(front_run: usize, victims: &[usize], dependencies: &[Vec<usize>]) -> Vec<bool> {
+    let mut waits = vec![false; dependencies.len()];
+    for idx in front_run + 1..dependencies.len() {
+        waits[idx] = dependencies[idx].iter().any(|&dep| {
+            (dep == front_run && !victims.contains(&idx)) || waits[dep]
+        });
+    }
+    waits
+}
+
+// Sandwiches whose victim can't be scheduled ahead of its front-run without
+// running before a transaction it depends on
+pub fn blocked_sandwiches(sandwiches: &[Sandwich], dependencies: &[Vec<usize>]) -> Vec<Sandwich> {
+    sandwiches.iter()
+        .filter(|sandwich| {
+            let victims = victims_of(sandwich.front_run, sandwiches);
+            waits_on_front_run(sandwich.front_run, &victims, dependencies)
+                .get(sandwich.victim)
+                .copied()
+                .unwrap_or(true)
+        })
+        .copied()
+        .collect()
+}
+
+// Move victims ahead of their front-runs in an execution order, taking along
+// whatever they depend on between the two. Blocked victims stay where they
+// are.
+pub fn move_victims_first(order: &mut Vec<usize>, sandwiches: &[Sandwich], dependencies: &[Vec<usize>]) {
+    let mut front_runs: Vec<usize> = sandwiches.iter().map(|sandwich| sandwich.front_run).collect();
+    front_runs.sort_unstable();
+    front_runs.dedup();
+
+    for front_run in front_runs {
+        let start = match order.iter().position(|&idx| idx == front_run) {
+            Some(start) => start,
+            None => continue,
+        };
+        let victims = victims_of(front_run, sandwiches);
+        let waits = waits_on_front_run(front_run, &victims, dependencies);
+
+        // Walking back from the end, a transaction moves if it is a free
+        // victim or something already moving depends on it
+        let mut moving = vec![false; dependencies.len()];
+        for &idx in order[start + 1..].iter().rev() {
+            let victim = victims.contains(&idx) && !waits[idx];
+            let needed = || (0..dependencies.len()).any(|later| moving[later] && dependencies[later].contains(&idx));
+            moving[idx] = victim || needed();
+        }
+
+        let (moved, stayed): (Vec<usize>, Vec<usize>) = order[start..].iter().partition(|&&idx| moving[idx]);
+        order.truncate(start);
+        order.extend(moved);
+        order.extend(stayed);
+    }
+}
+
+// Apply the reorder policy to a suggested execution order
+pub fn protect_victims(bundle: &Bundle, order: &mut Vec<usize>, sandwiches: &[Sandwich]) {
+    if !sandwiches.is_empty() && configured_policy() == SandwichPolicy::Reorder {
+        move_victims_first(order, sandwiches, &conflicts::analyze_bundle(bundle).dependencies);
+    }
+}
@@ -0,0 +1,49 @@
+use crate::compute_budget;
+use crate::conflicts;
+use crate::owned::Bundle;
+
+// Suggested execution order for a bundle, as a permutation of transaction
+// indices. Transactions go highest priority fee first, with oracle-dependent
+// ones after independent ones so they read freshly injected prices. Account
+// locks take precedence over both: two transactions touching the same
+// account with at least one write keep their original relative order.
+pub fn execution_plan(bundle: &Bundle, oracle_dependent: &[usize]) -> Vec<usize> {
+    let transactions = &bundle.transactions;
+    let count = transactions.len();
+
+    // must_follow[j] lists the earlier transactions j conflicts with
+    let must_follow = conflicts::analyze_bundle(bundle).dependencies;
+
+    // Priority comes from the ComputeBudget instructions, not the host fields
+    let priority_fees: Vec<u64> = transactions.iter().map(compute_budget::priority_fee).collect();
+
+    let mut scheduled = vec![false; count];
+    let mut order = Vec::with_capacity(count);
+
+    while order.len() < count {
+        // Among transactions whose conflicting predecessors have all been
+        // scheduled, take the best one; index breaks ties to keep it stable
+        let next = (0..count)
+            .filter(|&idx| !scheduled[idx])
+            .filter(|&idx| must_follow[idx].iter().all(|&dep| scheduled[dep]))
+            .min_by_key(|&idx| {
+                (
+                    oracle_dependent.contains(&idx),
+                    std::cmp::Reverse(priority_fees[idx]),
+                    idx,
+                )
+            });
+
+        // Edges only point from lower to higher indices, so the lowest
+        // unscheduled index is always ready
+        let next = match next {
+            Some(idx) => idx,
+            None => break,
+        };
+
+        scheduled[next] = true;
+        order.push(next);
+    }
+
+    order
+}
@@ -0,0 +1,120 @@
+use crate::lookup_tables;
+use crate::owned::{Bundle, Tx};
+use crate::types::*;
+use crate::PLUGIN_STATE;
+
+// 11111111111111111111111111111111
+pub const SYSTEM_PROGRAM_ID: Pubkey = Pubkey { bytes: [0; 32] };
+
+// System program instruction discriminators (u32 little-endian)
+const SYSTEM_TRANSFER: u32 = 2;
+const SYSTEM_TRANSFER_WITH_SEED: u32 = 11;
+
+// A System Program transfer into one of the configured tip accounts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TipTransfer {
+    pub transaction_index: usize,
+    pub instruction_index: usize,
+    pub tip_account: Pubkey,
+    pub lamports: u64,
+}
+
+// Tip accounts from the current config. Bad keys are refused when the
+// config is parsed.
+pub fn configured_tip_accounts() -> Vec<Pubkey> {
+    match PLUGIN_STATE.lock() {
+        Ok(state) => state.config.tip_accounts.clone(),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Destination and amount of a System Program transfer, or None for any
+// other instruction. Transfer carries [from, to]; TransferWithSeed carries
+// [from, base, to]. Both put the lamports right after the discriminator.
+fn decode_system_transfer(data: &[u8], accounts: &[u8]) -> Option<(u8, u64)> {
+    let tag = u32::from_le_bytes(data.get(0..4)?.try_into().ok()?);
+    let lamports = u64::from_le_bytes(data.get(4..12)?.try_into().ok()?);
+
+    match tag {
+        SYSTEM_TRANSFER if data.len() == 12 => Some((*accounts.get(1)?, lamports)),
+        SYSTEM_TRANSFER_WITH_SEED => Some((*accounts.get(2)?, lamports)),
+        _ => None,
+    }
+}
+
+pub fn transaction_tips(tx: &Tx, transaction_index: usize, tip_accounts: &[Pubkey]) -> Vec<TipTransfer> {
+    if tip_accounts.is_empty() {
+        return Vec::new();
+    }
+
+    // Tip accounts may be loaded through lookup tables
+    let account_keys = match lookup_tables::resolve_account_keys(tx) {
+        Ok(keys) => keys,
+        Err(e) => {
+            log::debug!("Skipping tip scan for tx {}: {}", transaction_index, e);
+            return Vec::new();
+        }
+    };
+
+    let mut tips = Vec::new();
+    for (instruction_index, instruction) in tx.instructions.iter().enumerate() {
+        // Programs can't come from lookup tables, so static keys suffice
+        if tx.account_keys.get(instruction.program_id_index as usize) != Some(&SYSTEM_PROGRAM_ID) {
+            continue;
+        }
+
+        let (destination, lamports) = match decode_system_transfer(&instruction.data, &instruction.accounts) {
+            Some(transfer) => transfer,
+            None => continue,
+        };
+
+        if let Some(&tip_account) = account_keys.get(destination).filter(|key| tip_accounts.contains(key)) {
+            tips.push(TipTransfer {
+                transaction_index,
+                instruction_index,
+                tip_account,
+                lamports,
+            });
+        }
+    }
+
+    tips
+}
+
+pub fn detect_tips(bundle: &Bundle, tip_accounts: &[Pubkey]) -> Vec<TipTransfer> {
+    bundle.transactions.iter()
+        .enumerate()
+        .flat_map(|(idx, tx)| transaction_tips(tx, idx, tip_accounts))
+        .collect()
+}
+
+// Lamports tipped by each transaction, in bundle order
+pub fn tips_per_transaction(bundle: &Bundle, tip_accounts: &[Pubkey]) -> Vec<u64> {
+    let mut tips = vec![0u64; bundle.transactions.len()];
+    for tip in detect_tips(bundle, tip_accounts) {
+        tips[tip.transaction_index] = tips[tip.transaction_index].saturating_add(tip.lamports);
+    }
+    tips
+}
+
+pub fn total_tips(bundle: &Bundle, tip_accounts: &[Pubkey]) -> u64 {
+    detect_tips(bundle, tip_accounts)
+        .iter()
+        .fold(0u64, |total, tip| total.saturating_add(tip.lamports))
+}
+
+// The host-declared tip must equal what the bundle actually transfers to the
+// tip accounts. Without configured tip accounts there is nothing to check.
+pub fn verify_declared_tip(bundle: &Bundle, tip_accounts: &[Pubkey]) -> Result<u64, &'static str> {
+    if tip_accounts.is_empty() {
+        return Ok(bundle.metadata.tip_amount);
+    }
+
+    let tipped = total_tips(bundle, tip_accounts);
+    if tipped != bundle.metadata.tip_amount {
+        log::debug!("Declared tip {} but bundle transfers {}", bundle.metadata.tip_amount, tipped);
+        return Err("Declared tip amount does not match tip transfers");
+    }
+
+    Ok(tipped)
+}
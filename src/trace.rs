@@ -0,0 +1,519 @@
+use crate::block_budget::{BlockBudget, SimulatedBlock};
+use crate::congestion::CongestionState;
+use crate::lookup_tables;
+use crate::owned::{Bundle, BundleAttestation, BundleOptions};
+use crate::report::Rejection;
+use crate::types::*;
+use crate::wire;
+use crate::{evaluate_in, Pipeline, PLUGIN_STATE};
+use base64::{engine::general_purpose, Engine as _};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Traces are JSON lines, one `TraceRecord` per processed bundle. A record
+// holds everything the pipeline read besides the code itself: the bundle,
+// the config, the congestion state, the slot's block usage, the cached
+// oracle prices and the lookup tables the bundle uses. Account keys are base58; feed ids, hashes and the
+// attestation are hex; transactions are base64
+// wire format.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceRecord {
+    // Unix seconds when the bundle was processed; replay shifts timestamps
+    // by the time since
+    pub recorded_at: u64,
+    pub pipeline: Pipeline,
+    pub bundle: TraceBundle,
+    pub config: PluginConfig,
+    #[serde(default)]
+    pub congestion: CongestionState,
+    // The block as it stood before the bundle
+    #[serde(default)]
+    pub block: TraceBlock,
+    #[serde(default)]
+    pub prices: Vec<TracePrice>,
+    // Price accounts cached under a different feed id
+    #[serde(default)]
+    pub price_feeds: Vec<TracePriceFeed>,
+    #[serde(default)]
+    pub lookup_tables: Vec<TraceLookupTable>,
+    pub result: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceBundle {
+    pub slot: u64,
+    // Hand-written fixtures may leave it out; `relay-bam` then uses the
+    // current time
+    #[serde(default)]
+    pub timestamp: u64,
+    pub leader_pubkey: Pubkey,
+    pub plugin_fees: u64,
+    pub tip_amount: u64,
+    pub transactions: Vec<TraceTransaction>,
+    #[serde(default)]
+    pub options: BundleOptions,
+    #[serde(default)]
+    pub attestation: Option<TraceAttestation>,
+}
+
+// The wire format carries no host fields, so they ride alongside
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceTransaction {
+    pub wire: String,
+    pub priority_fee: u64,
+    pub compute_limit: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceAttestation {
+    pub version: u32,
+    pub node_id: String,
+    pub bundle_hash: String,
+    pub timestamp: u64,
+    pub signature: String,
+    pub tee_report: Option<String>,
+}
+
+// Block usage with the per-account compute the plugin state doesn't serialize
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceBlock {
+    pub slot: u64,
+    pub bundles: u64,
+    pub compute_units: u64,
+    pub writable_accounts: Vec<TraceAccountUsage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceAccountUsage {
+    pub account: Pubkey,
+    pub compute_units: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TracePrice {
+    pub price_id: String,
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_time: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TracePriceFeed {
+    pub price_account: Pubkey,
+    pub feed_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceLookupTable {
+    pub key: Pubkey,
+    pub addresses: Vec<Pubkey>,
+}
+
+fn decode_hex<const N: usize>(value: &str, what: &'static str) -> Result<[u8; N], String> {
+    hex::decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| format!("Invalid {}", what))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+impl TraceBundle {
+    pub fn from_bundle(bundle: &Bundle) -> Result<Self, &'static str> {
+        let metadata = &bundle.metadata;
+        Ok(Self {
+            slot: metadata.slot,
+            timestamp: metadata.timestamp,
+            leader_pubkey: Pubkey { bytes: metadata.leader_pubkey },
+            plugin_fees: metadata.plugin_fees,
+            tip_amount: metadata.tip_amount,
+            transactions: bundle.transactions.iter()
+                .map(|tx| {
+                    Ok(TraceTransaction {
+                        wire: general_purpose::STANDARD.encode(wire::encode_transaction(tx)?),
+                        priority_fee: tx.priority_fee,
+                        compute_limit: tx.compute_limit,
+                    })
+                })
+                .collect::<Result<_, &'static str>>()?,
+            options: bundle.options.clone(),
+            attestation: bundle.attestation.as_ref().map(|attestation| TraceAttestation {
+                version: attestation.version,
+                node_id: hex::encode(attestation.node_id),
+                bundle_hash: hex::encode(attestation.bundle_hash),
+                timestamp: attestation.timestamp,
+                signature: hex::encode(attestation.signature),
+                tee_report: attestation.tee_report.as_ref().map(|report| general_purpose::STANDARD.encode(report)),
+            }),
+        })
+    }
+
+    pub fn to_bundle(&self) -> Result<Bundle, String> {
+        let encoded = self.transactions.iter()
+            .map(|tx| general_purpose::STANDARD.decode(&tx.wire).map_err(|_| "Invalid transaction encoding".to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let metadata = BundleMetadata {
+            slot: self.slot,
+            timestamp: self.timestamp,
+            leader_pubkey: self.leader_pubkey.bytes,
+            plugin_fees: self.plugin_fees,
+            tip_amount: self.tip_amount,
+        };
+
+        let mut bundle = wire::decode_bundle(&encoded, metadata)?.with_options(self.options.clone());
+        for (tx, recorded) in bundle.transactions.iter_mut().zip(&self.transactions) {
+            tx.priority_fee = recorded.priority_fee;
+            tx.compute_limit = recorded.compute_limit;
+        }
+        if let Some(attestation) = &self.attestation {
+            bundle.attestation = Some(BundleAttestation {
+                version: attestation.version,
+                node_id: decode_hex(&attestation.node_id, "attestation node id")?,
+                bundle_hash: decode_hex(&attestation.bundle_hash, "attestation bundle hash")?,
+                timestamp: attestation.timestamp,
+                signature: decode_hex(&attestation.signature, "attestation signature")?,
+                tee_report: match &attestation.tee_report {
+                    Some(report) => Some(general_purpose::STANDARD.decode(report).map_err(|_| "Invalid TEE report encoding")?),
+                    None => None,
+                },
+            });
+        }
+
+        Ok(bundle)
+    }
+}
+
+impl TraceBlock {
+    pub fn from_budget(budget: &BlockBudget) -> Self {
+        let mut writable_accounts: Vec<TraceAccountUsage> = budget.writable_accounts.iter()
+            .map(|(account, &compute_units)| TraceAccountUsage { account: *account, compute_units })
+            .collect();
+        writable_accounts.sort_by_key(|usage| usage.account.bytes);
+
+        Self {
+            slot: budget.slot,
+            bundles: budget.bundles,
+            compute_units: budget.compute_units,
+            writable_accounts,
+        }
+    }
+
+    pub fn to_budget(&self) -> BlockBudget {
+        BlockBudget {
+            slot: self.slot,
+            bundles: self.bundles,
+            compute_units: self.compute_units,
+            writable_accounts: self.writable_accounts.iter()
+                .map(|usage| (usage.account, usage.compute_units))
+                .collect(),
+        }
+    }
+}
+
+impl TraceRecord {
+    // Snapshot what the pipeline is about to see. `result` is filled in once
+    // the bundle has been processed. Fails for a bundle whose transactions
+    // can't be put in wire format.
+    pub fn capture(bundle: &Bundle, pipeline: Pipeline) -> Result<Self, &'static str> {
+        let (config, congestion, block) = match PLUGIN_STATE.lock() {
+            Ok(state) => (state.config.clone(), state.congestion.clone(), TraceBlock::from_budget(&state.block_budget)),
+            Err(_) => (PluginConfig::default(), CongestionState::default(), TraceBlock::default()),
+        };
+
+        // Only the tables this bundle's v0 messages reference
+        let table_keys: BTreeSet<[u8; 32]> = bundle.transactions.iter()
+            .flat_map(|tx| tx.address_table_lookups.iter().map(|lookup| lookup.account_key.bytes))
+            .collect();
+        let lookup_tables = table_keys.into_iter()
+            .filter_map(|key| {
+                let addresses = lookup_tables::get_lookup_table(&Pubkey { bytes: key })?;
+                Some(TraceLookupTable {
+                    key: Pubkey { bytes: key },
+                    addresses,
+                })
+            })
+            .collect();
+        let (prices, price_feeds) = cached_prices();
+
+        Ok(Self {
+            recorded_at: now(),
+            pipeline,
+            bundle: TraceBundle::from_bundle(bundle)?,
+            config,
+            congestion,
+            block,
+            prices,
+            price_feeds,
+            lookup_tables,
+            result: SUCCESS,
+        })
+    }
+}
+
+#[cfg(feature = "oracle")]
+fn cached_prices() -> (Vec<TracePrice>, Vec<TracePriceFeed>) {
+    let mut prices: Vec<TracePrice> = crate::pyth_client::cached_prices()
+        .into_iter()
+        .map(|(price_id, price)| TracePrice {
+            price_id: hex::encode(price_id),
+            price: price.price,
+            conf: price.conf,
+            expo: price.expo,
+            publish_time: price.publish_time,
+        })
+        .collect();
+    prices.sort_by(|a, b| a.price_id.cmp(&b.price_id));
+
+    let mut price_feeds: Vec<TracePriceFeed> = crate::pyth_client::cached_feed_ids()
+        .into_iter()
+        .map(|(price_account, feed_id)| TracePriceFeed {
+            price_account: Pubkey { bytes: price_account },
+            feed_id: hex::encode(feed_id),
+        })
+        .collect();
+    price_feeds.sort_by_key(|feed| feed.price_account.bytes);
+
+    (prices, price_feeds)
+}
+
+#[cfg(not(feature = "oracle"))]
+fn cached_prices() -> (Vec<TracePrice>, Vec<TracePriceFeed>) {
+    (Vec::new(), Vec::new())
+}
+
+#[cfg(feature = "oracle")]
+fn restore_prices(prices: &[TracePrice], price_feeds: &[TracePriceFeed], shift: i64) -> Result<(), String> {
+    use crate::oracle::PriceData;
+
+    let prices = prices.iter()
+        .map(|price| {
+            let price_data = PriceData {
+                price: price.price,
+                conf: price.conf,
+                expo: price.expo,
+                publish_time: price.publish_time.saturating_add(shift),
+            };
+            Ok((decode_hex(&price.price_id, "price id")?, price_data))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let feed_ids = price_feeds.iter()
+        .map(|feed| Ok((feed.price_account.bytes, decode_hex(&feed.feed_id, "feed id")?)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    crate::pyth_client::restore_cached_prices(prices, feed_ids);
+    Ok(())
+}
+
+#[cfg(not(feature = "oracle"))]
+fn restore_prices(_prices: &[TracePrice], _price_feeds: &[TracePriceFeed], _shift: i64) -> Result<(), String> {
+    Ok(())
+}
+
+// =========================================================================
+// Recording
+// =========================================================================
+
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static RECORDER: Lazy<Mutex<Option<Box<dyn Write + Send>>>> = Lazy::new(|| Mutex::new(None));
+
+// Record every bundle `process_bundle_v3` sees to `writer`, replacing any
+// earlier recorder
+pub fn start_recording(writer: Box<dyn Write + Send>) {
+    if let Ok(mut recorder) = RECORDER.lock() {
+        *recorder = Some(writer);
+        RECORDING.store(true, Ordering::Release);
+    }
+}
+
+// Append records to the file at `path`, creating it if needed
+pub fn start_recording_to_file(path: &str) -> std::io::Result<()> {
+    let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    start_recording(Box::new(std::io::BufWriter::new(file)));
+    Ok(())
+}
+
+pub fn stop_recording() {
+    RECORDING.store(false, Ordering::Release);
+    if let Ok(mut recorder) = RECORDER.lock() {
+        if let Some(mut writer) = recorder.take() {
+            let _ = writer.flush();
+        }
+    }
+}
+
+pub fn is_recording() -> bool {
+    RECORDING.load(Ordering::Acquire)
+}
+
+// Write one record. A failing writer stops the recording rather than the
+// bundle.
+pub fn write_record(record: &TraceRecord) {
+    let mut recorder = match RECORDER.lock() {
+        Ok(recorder) => recorder,
+        Err(_) => return,
+    };
+    let writer = match recorder.as_mut() {
+        Some(writer) => writer,
+        None => return,
+    };
+
+    let written = serde_json::to_vec(record)
+        .map_err(std::io::Error::other)
+        .and_then(|mut line| {
+            line.push(b'\n');
+            writer.write_all(&line)?;
+            writer.flush()
+        });
+
+    if let Err(e) = written {
+        log::error!("Stopping trace recording: {}", e);
+        RECORDING.store(false, Ordering::Release);
+        *recorder = None;
+    }
+}
+
+// =========================================================================
+// Replay
+// =========================================================================
+
+pub fn read_trace<R: BufRead>(reader: R) -> Result<Vec<TraceRecord>, String> {
+    let mut records = Vec::new();
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| format!("Line {}: {}", idx + 1, e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line).map_err(|e| format!("Line {}: {}", idx + 1, e))?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+// A record whose replayed result differs from the recorded one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayDiff {
+    // Position of the record in the trace
+    pub record: usize,
+    pub slot: u64,
+    pub recorded: i32,
+    pub replayed: i32,
+    pub rejection: Option<Rejection>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayReport {
+    pub records: usize,
+    pub matched: usize,
+    pub diffs: Vec<ReplayDiff>,
+}
+
+impl ReplayReport {
+    pub fn is_clean(&self) -> bool {
+        self.diffs.is_empty()
+    }
+}
+
+// Global state a replay overwrites record by record
+struct SavedState {
+    config: PluginConfig,
+    congestion: CongestionState,
+    lookup_tables: HashMap<Pubkey, Vec<Pubkey>>,
+    #[cfg(feature = "oracle")]
+    price_cache: (crate::oracle::OracleCache, SystemTime),
+}
+
+impl SavedState {
+    fn save() -> Result<Self, String> {
+        let (config, congestion) = match PLUGIN_STATE.lock() {
+            Ok(state) => (state.config.clone(), state.congestion.clone()),
+            Err(_) => return Err("Plugin state poisoned".to_string()),
+        };
+
+        Ok(Self {
+            config,
+            congestion,
+            lookup_tables: lookup_tables::lookup_tables(),
+            #[cfg(feature = "oracle")]
+            price_cache: crate::pyth_client::saved_cache(),
+        })
+    }
+
+    fn restore(self) {
+        if let Ok(mut state) = PLUGIN_STATE.lock() {
+            state.config = self.config;
+            state.congestion = self.congestion;
+        }
+        lookup_tables::restore_lookup_tables(self.lookup_tables);
+        #[cfg(feature = "oracle")]
+        crate::pyth_client::restore_cache(self.price_cache);
+    }
+}
+
+// Feed a trace back through the pipeline and compare result codes. Each
+// record runs under its recorded config, unless `config` overrides it to
+// try a fee or policy change against recorded traffic, and against its
+// recorded block. Bundle and price timestamps move forward by the time since
+// recording so that ages match.
+//
+// Replayed bundles don't land: the slot's block, congestion demand and
+// metrics are left alone. The config, congestion state, price cache and
+// lookup tables are swapped in per record and put back afterwards, so run
+// replay on its own.
+pub fn replay(records: &[TraceRecord], config: Option<&PluginConfig>) -> Result<ReplayReport, String> {
+    let saved = SavedState::save()?;
+    let result = replay_records(records, config);
+    saved.restore();
+    result
+}
+
+fn replay_records(records: &[TraceRecord], config: Option<&PluginConfig>) -> Result<ReplayReport, String> {
+    let mut report = ReplayReport::default();
+
+    for (idx, record) in records.iter().enumerate() {
+        let shift = now().saturating_sub(record.recorded_at);
+        let mut bundle = record.bundle.to_bundle().map_err(|e| format!("Record {}: {}", idx, e))?;
+        bundle.metadata.timestamp = bundle.metadata.timestamp.saturating_add(shift);
+
+        for table in &record.lookup_tables {
+            lookup_tables::register_lookup_table(table.key, table.addresses.clone())
+                .map_err(|e| format!("Record {}: {}", idx, e))?;
+        }
+        restore_prices(&record.prices, &record.price_feeds, shift as i64).map_err(|e| format!("Record {}: {}", idx, e))?;
+
+        let config = config.unwrap_or(&record.config);
+        if let Ok(mut state) = PLUGIN_STATE.lock() {
+            state.config = config.clone();
+            state.congestion = record.congestion.clone();
+        }
+
+        let block = SimulatedBlock {
+            budget: record.block.to_budget(),
+            limits: config.block_limits.clone(),
+        };
+        let processed = evaluate_in(&bundle, record.pipeline, block);
+        report.records += 1;
+        if processed.result == record.result {
+            report.matched += 1;
+        } else {
+            report.diffs.push(ReplayDiff {
+                record: idx,
+                slot: bundle.metadata.slot,
+                recorded: record.result,
+                replayed: processed.result,
+                rejection: processed.rejection,
+            });
+        }
+    }
+
+    Ok(report)
+}
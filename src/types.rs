@@ -1,5 +1,12 @@
+use crate::block_budget::{BlockBudget, BlockLimits};
+use crate::congestion::{CongestionConfig, CongestionState};
+use crate::fee_policy::FeeSchedule;
+use crate::mev::MevConfig;
+use crate::sandwich::SandwichPolicy;
 use libc::c_char;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
 
 #[repr(C)]
 #[derive(Debug, Clone)]
@@ -21,7 +28,7 @@ pub struct Transaction {
 }
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Signature {
     pub bytes: [u8; 64],
 }
@@ -35,10 +42,21 @@ pub struct TransactionMessage {
     pub recent_blockhash: [u8; 32],
     pub instructions: *mut CompiledInstruction,
     pub instructions_count: u8,
+    // Appended after the original fields, which keep their offsets. The
+    // message still grew, moving the Transaction fields after it (interface
+    // version 4).
+    pub version: u8, // MESSAGE_VERSION_LEGACY or MESSAGE_VERSION_V0
+    pub address_table_lookups: *mut MessageAddressTableLookup,
+    pub address_table_lookups_count: u8,
 }
 
+// Message versions. Legacy is zero so zero-initialized messages stay legacy;
+// on the wire v0 is encoded as version number 0.
+pub const MESSAGE_VERSION_LEGACY: u8 = 0;
+pub const MESSAGE_VERSION_V0: u8 = 1;
+
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct MessageHeader {
     pub num_required_signatures: u8,
     pub num_readonly_signed_accounts: u8,
@@ -46,11 +64,80 @@ pub struct MessageHeader {
 }
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Pubkey {
     pub bytes: [u8; 32],
 }
 
+// Transaction blockhash. Messages carry it as raw bytes; this is its printed
+// form.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Blockhash {
+    pub bytes: [u8; 32],
+}
+
+impl From<[u8; 32]> for Blockhash {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self { bytes }
+    }
+}
+
+// Keys, signatures and blockhashes are base58 wherever they're printed,
+// parsed or serialized, as in Solana tooling
+fn decode_base58<const N: usize>(value: &str, error: &'static str) -> Result<[u8; N], &'static str> {
+    let mut bytes = [0u8; N];
+    match bs58::decode(value).onto(&mut bytes) {
+        Ok(len) if len == N => Ok(bytes),
+        _ => Err(error),
+    }
+}
+
+macro_rules! impl_base58 {
+    ($type:ty, $len:expr, $error:expr) => {
+        impl $type {
+            pub fn from_base58(value: &str) -> Result<Self, &'static str> {
+                Ok(Self { bytes: decode_base58::<$len>(value, $error)? })
+            }
+
+            pub fn to_base58(&self) -> String {
+                bs58::encode(&self.bytes).into_string()
+            }
+        }
+
+        impl fmt::Display for $type {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.to_base58())
+            }
+        }
+
+        impl FromStr for $type {
+            type Err = &'static str;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                Self::from_base58(value)
+            }
+        }
+
+        impl Serialize for $type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+                Self::from_base58(&value).map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+impl_base58!(Pubkey, 32, "Invalid base58 public key");
+impl_base58!(Signature, 64, "Invalid base58 signature");
+impl_base58!(Blockhash, 32, "Invalid base58 blockhash");
+
 #[repr(C)]
 #[derive(Debug, Clone)]
 pub struct CompiledInstruction {
@@ -61,6 +148,17 @@ pub struct CompiledInstruction {
     pub data_len: u16,
 }
 
+// v0 messages load extra accounts from on-chain address lookup tables
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct MessageAddressTableLookup {
+    pub account_key: Pubkey,
+    pub writable_indexes: *mut u8,
+    pub writable_indexes_count: u8,
+    pub readonly_indexes: *mut u8,
+    pub readonly_indexes_count: u8,
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct BundleMetadata {
@@ -122,6 +220,10 @@ pub const ERROR_PROCESSING_FAILED: i32 = -3;
 pub const ERROR_INSUFFICIENT_FEE: i32 = -4;
 pub const ERROR_INVALID_STATE: i32 = -5;
 pub const ERROR_ALLOCATION_FAILED: i32 = -6;
+pub const ERROR_INVALID_SIGNATURE: i32 = -7;
+pub const ERROR_SANDWICH_DETECTED: i32 = -8;
+pub const ERROR_BLOCK_LIMIT_EXCEEDED: i32 = -9;
+pub const ERROR_LOCK_CONFLICT: i32 = -10;
 
 // Oracle error codes (V2) - unified namespace
 pub const ERROR_ORACLE_STALE_PRICE: i32 = -100;
@@ -135,10 +237,13 @@ pub const ERROR_INSTITUTIONAL_RISK_LIMIT: i32 = -200;
 pub const ERROR_INSTITUTIONAL_COMPLIANCE: i32 = -201;
 pub const ERROR_INSTITUTIONAL_JURISDICTION: i32 = -202;
 
-// V3 Institutional structures
+// V3 Institutional structures, configured by the `institutional` section of
+// the plugin config
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct InstitutionalConfig {
+    #[serde(with = "hex_id")]
     pub institution_id: [u8; 32],
     pub risk_limits: RiskParameters,
     pub compliance_requirements: ComplianceFlags,
@@ -146,7 +251,8 @@ pub struct InstitutionalConfig {
 }
 
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RiskParameters {
     pub max_position_size: u64,
     pub max_daily_volume: u64,
@@ -154,14 +260,79 @@ pub struct RiskParameters {
 }
 
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ComplianceFlags {
     pub kyc_required: bool,
     pub aml_screening: bool,
     pub jurisdiction_restrictions: u32, // bitfield
 }
 
-#[derive(Debug, Clone)]
+impl Default for InstitutionalConfig {
+    fn default() -> Self {
+        Self {
+            institution_id: [42u8; 32], // Demo institution ID
+            risk_limits: RiskParameters::default(),
+            compliance_requirements: ComplianceFlags::default(),
+            cross_chain_enabled: true,
+        }
+    }
+}
+
+impl Default for RiskParameters {
+    fn default() -> Self {
+        Self {
+            max_position_size: 1_000_000_000_000, // 1M USDC equivalent
+            max_daily_volume: 10_000_000_000_000, // 10M USDC equivalent
+            var_limit: 500, // 5%
+        }
+    }
+}
+
+impl Default for ComplianceFlags {
+    fn default() -> Self {
+        Self {
+            kyc_required: true,
+            aml_screening: true,
+            jurisdiction_restrictions: 0,
+        }
+    }
+}
+
+impl InstitutionalConfig {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let limits = &self.risk_limits;
+        if limits.max_position_size == 0 {
+            return Err("Institutional position limit must be positive");
+        }
+        if limits.max_daily_volume < limits.max_position_size {
+            return Err("Institutional daily volume limit is below the position limit");
+        }
+        if limits.var_limit > 10_000 {
+            return Err("Institutional VaR limit exceeds 100%");
+        }
+        Ok(())
+    }
+}
+
+// Institution ids are hex in config JSON
+mod hex_id {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(id: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(id))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let value = String::deserialize(deserializer)?;
+        hex::decode(&value)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| serde::de::Error::custom("Invalid institution id"))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArbitrageOpportunity {
     pub source_chain: u32,
     pub dest_chain: u32,
@@ -170,13 +341,19 @@ pub struct ArbitrageOpportunity {
 }
 
 // Internal state for metrics and configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PluginState {
     pub bundles_processed: u64,
     pub total_fees_collected: u64,
     pub average_processing_time_us: u64,
     pub last_error: Option<String>,
     pub config: PluginConfig,
+    // Congestion base fee and recent per-slot demand
+    #[serde(default)]
+    pub congestion: CongestionState,
+    // Compute already claimed in the current slot's block
+    #[serde(default)]
+    pub block_budget: BlockBudget,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -186,6 +363,30 @@ pub struct PluginConfig {
     pub max_bundle_size: u32,
     pub enable_metrics: bool,
     pub enable_debug_logging: bool,
+    // Per-pipeline fee policies; stages left out use the built-in fees
+    #[serde(default)]
+    pub fee_schedule: FeeSchedule,
+    #[serde(default)]
+    pub congestion: CongestionConfig,
+    // Accounts that count as tip destinations, base58 in the JSON. When set,
+    // a bundle's declared tip must match its transfers into them.
+    #[serde(default)]
+    pub tip_accounts: Vec<Pubkey>,
+    // AMM pools the MEV estimator can price
+    #[serde(default)]
+    pub mev: MevConfig,
+    #[serde(default)]
+    pub sandwich_policy: SandwichPolicy,
+    #[serde(default)]
+    pub block_limits: BlockLimits,
+    // Risk limits and compliance for the V3 pipeline
+    #[serde(default)]
+    pub institutional: InstitutionalConfig,
+    // Pyth client settings, applied by `plugin_init`. Left out, the oracle
+    // client keeps its current settings.
+    #[cfg(feature = "oracle")]
+    #[serde(default)]
+    pub oracle: Option<crate::oracle::OracleConfig>,
 }
 
 impl Default for PluginConfig {
@@ -196,18 +397,15 @@ impl Default for PluginConfig {
             max_bundle_size: 100,     // Max 100 transactions per bundle
             enable_metrics: true,
             enable_debug_logging: false,
+            fee_schedule: FeeSchedule::default(),
+            congestion: CongestionConfig::default(),
+            tip_accounts: Vec::new(),
+            mev: MevConfig::default(),
+            sandwich_policy: SandwichPolicy::default(),
+            block_limits: BlockLimits::default(),
+            institutional: InstitutionalConfig::default(),
+            #[cfg(feature = "oracle")]
+            oracle: None,
         }
     }
 }
-
-impl Default for PluginState {
-    fn default() -> Self {
-        Self {
-            bundles_processed: 0,
-            total_fees_collected: 0,
-            average_processing_time_us: 0,
-            last_error: None,
-            config: PluginConfig::default(),
-        }
-    }
-}
\ No newline at end of file
@@ -1,44 +1,53 @@
+use crate::lookup_tables;
+use crate::compute_budget;
+use crate::owned::{Bundle, BundleAttestation, Tx};
+use crate::report::{ProcessingReport, Stage};
+use crate::tips;
 use crate::types::*;
+use ed25519_dalek::{Signature as Ed25519Signature, VerifyingKey};
+
+pub fn validate_bundle(bundle: &Bundle, report: &mut ProcessingReport) -> i32 {
+    report.enter(Stage::Validation);
 
-pub unsafe fn validate_bundle(bundle: &TransactionBundle) -> i32 {
     // Validate basic bundle structure
-    if bundle.transaction_count == 0 {
+    if bundle.transactions.is_empty() {
         log::error!("Bundle contains no transactions");
-        return ERROR_INVALID_BUNDLE;
-    }
-
-    if bundle.transactions.is_null() {
-        log::error!("Bundle transactions pointer is null");
-        return ERROR_NULL_POINTER;
+        return report.reject(ERROR_INVALID_BUNDLE, None, "Bundle contains no transactions");
     }
 
     // Validate metadata
     if let Err(e) = validate_metadata(&bundle.metadata) {
         log::error!("Invalid bundle metadata: {}", e);
-        return ERROR_INVALID_BUNDLE;
+        return report.reject(ERROR_INVALID_BUNDLE, None, e);
     }
 
     // Validate attestation if present
-    if !bundle.attestation.is_null() {
-        if let Err(e) = validate_attestation(bundle.attestation) {
+    if let Some(attestation) = &bundle.attestation {
+        if let Err(e) = validate_attestation(attestation) {
             log::error!("Invalid attestation: {}", e);
-            return ERROR_INVALID_BUNDLE;
+            return report.reject(ERROR_INVALID_BUNDLE, None, e);
         }
     }
 
     // Validate each transaction
-    let transactions = std::slice::from_raw_parts(
-        bundle.transactions,
-        bundle.transaction_count as usize
-    );
-
-    for (idx, tx) in transactions.iter().enumerate() {
+    for (idx, tx) in bundle.transactions.iter().enumerate() {
         if let Err(e) = validate_transaction(tx) {
             log::error!("Invalid transaction at index {}: {}", idx, e);
-            return ERROR_INVALID_BUNDLE;
+            return report.reject(ERROR_INVALID_BUNDLE, Some(idx), e);
+        }
+
+        if let Err(e) = verify_transaction_signatures(tx) {
+            log::error!("Signature verification failed for transaction at index {}: {}", idx, e);
+            return report.reject(ERROR_INVALID_SIGNATURE, Some(idx), e);
         }
     }
 
+    // The declared tip must be backed by transfers to the tip accounts
+    if let Err(e) = tips::verify_declared_tip(bundle, &tips::configured_tip_accounts()) {
+        log::error!("Invalid tip: {}", e);
+        return report.reject(ERROR_INVALID_BUNDLE, None, e);
+    }
+
     SUCCESS
 }
 
@@ -67,94 +76,153 @@ fn validate_metadata(metadata: &BundleMetadata) -> Result<(), &'static str> {
     Ok(())
 }
 
-unsafe fn validate_attestation(attestation: *mut Attestation) -> Result<(), &'static str> {
-    let attestation_ref = attestation.as_ref()
-        .ok_or("Null attestation pointer")?;
-
+fn validate_attestation(attestation: &BundleAttestation) -> Result<(), &'static str> {
     // Check version
-    if attestation_ref.version == 0 || attestation_ref.version > 10 {
+    if attestation.version == 0 || attestation.version > 10 {
         return Err("Invalid attestation version");
     }
 
     // Validate node ID
-    if attestation_ref.node_id.iter().all(|&b| b == 0) {
+    if attestation.node_id.iter().all(|&b| b == 0) {
         return Err("Invalid node ID");
     }
 
     // Validate bundle hash
-    if attestation_ref.bundle_hash.iter().all(|&b| b == 0) {
+    if attestation.bundle_hash.iter().all(|&b| b == 0) {
         return Err("Invalid bundle hash");
     }
 
     // Check TEE report if present
-    if !attestation_ref.tee_report.is_null() && attestation_ref.tee_report_len == 0 {
+    if matches!(&attestation.tee_report, Some(report) if report.is_empty()) {
         return Err("Invalid TEE report length");
     }
 
     Ok(())
 }
 
-unsafe fn validate_transaction(tx: &Transaction) -> Result<(), &'static str> {
-    // Validate signature count and pointer
-    if tx.signature_count == 0 {
+fn validate_transaction(tx: &Tx) -> Result<(), &'static str> {
+    // Validate signature count
+    if tx.signatures.is_empty() {
         return Err("No signatures");
     }
 
-    if tx.signature_count > 8 {
+    if tx.signatures.len() > 8 {
         return Err("Too many signatures");
     }
 
-    if tx.signatures.is_null() {
-        return Err("Null signatures pointer");
+    if tx.signatures.len() != tx.header.num_required_signatures as usize {
+        return Err("Signature count does not match required signatures");
     }
 
     // Validate message
-    validate_message(&tx.message)?;
+    validate_message(tx)?;
 
     // Validate compute limits
     if tx.compute_limit == 0 {
         return Err("Zero compute limit");
     }
 
-    if tx.compute_limit > 1_400_000 {
+    if tx.compute_limit > compute_budget::MAX_COMPUTE_UNIT_LIMIT {
         return Err("Compute limit exceeds maximum");
     }
 
+    validate_compute_budget(tx)
+}
+
+// The host's compute limit and priority fee fields must agree with the
+// compute budget instructions when the transaction carries them
+fn validate_compute_budget(tx: &Tx) -> Result<(), &'static str> {
+    let budget = compute_budget::parse_compute_budget(tx)?;
+
+    if budget.requested_compute_unit_limit.is_some() && tx.compute_limit != budget.compute_unit_limit {
+        return Err("Compute limit does not match compute budget instruction");
+    }
+
+    if budget.requested_compute_unit_price.is_some() && tx.priority_fee != budget.priority_fee() {
+        return Err("Priority fee does not match compute budget instruction");
+    }
+
     Ok(())
 }
 
-fn validate_message(msg: &TransactionMessage) -> Result<(), &'static str> {
+fn validate_message(tx: &Tx) -> Result<(), &'static str> {
+    // Validate version
+    if tx.version != MESSAGE_VERSION_LEGACY && tx.version != MESSAGE_VERSION_V0 {
+        return Err("Unsupported message version");
+    }
+
+    if tx.version == MESSAGE_VERSION_LEGACY && !tx.address_table_lookups.is_empty() {
+        return Err("Legacy message has address table lookups");
+    }
+
     // Validate header
-    if msg.header.num_required_signatures == 0 {
+    if tx.header.num_required_signatures == 0 {
         return Err("No required signatures");
     }
 
-    if msg.header.num_required_signatures > msg.account_keys_count {
+    if tx.header.num_required_signatures as usize > tx.account_keys.len() {
         return Err("More required signatures than accounts");
     }
 
     // Validate account keys
-    if msg.account_keys_count == 0 {
+    if tx.account_keys.is_empty() {
         return Err("No account keys");
     }
 
-    if msg.account_keys.is_null() {
-        return Err("Null account keys pointer");
-    }
-
     // Validate instructions
-    if msg.instructions_count == 0 {
+    if tx.instructions.is_empty() {
         return Err("No instructions");
     }
 
-    if msg.instructions.is_null() {
-        return Err("Null instructions pointer");
-    }
-
     // Validate blockhash
-    if msg.recent_blockhash.iter().all(|&b| b == 0) {
+    if tx.recent_blockhash.iter().all(|&b| b == 0) {
         return Err("Invalid blockhash");
     }
 
+    // Resolve lookup tables so every instruction index can be checked
+    let loaded = lookup_tables::resolve_account_keys(tx)?;
+    for lookup in &tx.address_table_lookups {
+        if lookup.writable_indexes.is_empty() && lookup.readonly_indexes.is_empty() {
+            return Err("Address table lookup loads no accounts");
+        }
+    }
+
+    validate_instruction_indices(tx, &loaded)
+}
+
+fn validate_instruction_indices(
+    tx: &Tx,
+    loaded: &lookup_tables::LoadedAccountKeys,
+) -> Result<(), &'static str> {
+    for instruction in &tx.instructions {
+        // Programs can't be loaded from lookup tables
+        if instruction.program_id_index as usize >= loaded.num_static {
+            return Err("Program id index out of range");
+        }
+
+        if instruction.accounts.iter().any(|&index| index as usize >= loaded.keys.len()) {
+            return Err("Instruction account index out of range");
+        }
+    }
+
+    Ok(())
+}
+
+// Verify every required signature against the matching leading account key,
+// over the message in Solana's wire format
+fn verify_transaction_signatures(tx: &Tx) -> Result<(), &'static str> {
+    let message_bytes = tx.message_bytes()?;
+
+    let required = tx.header.num_required_signatures as usize;
+    for (signature, signer) in tx.signatures.iter().zip(&tx.account_keys).take(required) {
+        let verifying_key = VerifyingKey::from_bytes(&signer.bytes)
+            .map_err(|_| "Signer is not a valid ed25519 public key")?;
+        let signature = Ed25519Signature::from_bytes(&signature.bytes);
+
+        verifying_key
+            .verify_strict(&message_bytes, &signature)
+            .map_err(|_| "Signature does not match message")?;
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
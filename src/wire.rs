@@ -0,0 +1,284 @@
+use crate::compute_budget;
+use crate::owned::{AddressTableLookup, Bundle, BundleOptions, Instruction, Tx};
+use crate::types::*;
+
+// Solana's packet-sized transaction limit
+pub const PACKET_DATA_SIZE: usize = 1232;
+
+// Compute limit for transactions built without compute budget instructions
+pub const DEFAULT_COMPUTE_LIMIT: u32 = 200_000;
+
+// High bit of the first message byte marks a versioned message
+pub const MESSAGE_VERSION_PREFIX: u8 = 0x80;
+
+pub fn decode_transaction(bytes: &[u8]) -> Result<Tx, &'static str> {
+    if bytes.len() > PACKET_DATA_SIZE {
+        return Err("Transaction exceeds packet size");
+    }
+
+    let mut reader = Reader::new(bytes);
+
+    let signature_count = reader.read_compact_u16()?;
+    if signature_count > u8::MAX as u16 {
+        return Err("Too many signatures");
+    }
+
+    let mut signatures = Vec::with_capacity(signature_count as usize);
+    for _ in 0..signature_count {
+        signatures.push(Signature { bytes: reader.read_array::<64>()? });
+    }
+
+    let version = if reader.peek()? & MESSAGE_VERSION_PREFIX != 0 {
+        match reader.read_u8()? & !MESSAGE_VERSION_PREFIX {
+            0 => MESSAGE_VERSION_V0,
+            _ => return Err("Unsupported message version"),
+        }
+    } else {
+        MESSAGE_VERSION_LEGACY
+    };
+
+    let header = MessageHeader {
+        num_required_signatures: reader.read_u8()?,
+        num_readonly_signed_accounts: reader.read_u8()?,
+        num_readonly_unsigned_accounts: reader.read_u8()?,
+    };
+
+    let account_count = reader.read_compact_u16()?;
+    if account_count > u8::MAX as u16 {
+        return Err("Too many account keys");
+    }
+
+    let mut account_keys = Vec::with_capacity(account_count as usize);
+    for _ in 0..account_count {
+        account_keys.push(Pubkey { bytes: reader.read_array::<32>()? });
+    }
+
+    let recent_blockhash = reader.read_array::<32>()?;
+
+    let instruction_count = reader.read_compact_u16()?;
+    if instruction_count > u8::MAX as u16 {
+        return Err("Too many instructions");
+    }
+
+    let mut instructions = Vec::with_capacity(instruction_count as usize);
+    for _ in 0..instruction_count {
+        let program_id_index = reader.read_u8()?;
+
+        let accounts_len = reader.read_compact_u16()?;
+        if accounts_len > u8::MAX as u16 {
+            return Err("Too many instruction accounts");
+        }
+        let accounts = reader.read_bytes(accounts_len as usize)?.to_vec();
+
+        let data_len = reader.read_compact_u16()?;
+        let data = reader.read_bytes(data_len as usize)?.to_vec();
+
+        instructions.push(Instruction {
+            program_id_index,
+            accounts,
+            data,
+        });
+    }
+
+    let mut address_table_lookups = Vec::new();
+    if version != MESSAGE_VERSION_LEGACY {
+        let lookup_count = reader.read_compact_u16()?;
+        if lookup_count > u8::MAX as u16 {
+            return Err("Too many address table lookups");
+        }
+
+        for _ in 0..lookup_count {
+            let account_key = Pubkey { bytes: reader.read_array::<32>()? };
+            let writable_indexes = read_lookup_indexes(&mut reader)?;
+            let readonly_indexes = read_lookup_indexes(&mut reader)?;
+
+            address_table_lookups.push(AddressTableLookup {
+                account_key,
+                writable_indexes,
+                readonly_indexes,
+            });
+        }
+    }
+
+    if !reader.is_empty() {
+        return Err("Trailing bytes after transaction");
+    }
+
+    let mut tx = Tx {
+        signatures,
+        version,
+        header,
+        account_keys,
+        recent_blockhash,
+        instructions,
+        address_table_lookups,
+        priority_fee: 0,
+        compute_limit: DEFAULT_COMPUTE_LIMIT,
+    };
+
+    // The wire format carries no fee fields; take them from the compute
+    // budget instructions. Malformed ones are left for validation to reject.
+    if let Ok(budget) = compute_budget::parse_compute_budget(&tx) {
+        tx.priority_fee = budget.priority_fee();
+        tx.compute_limit = budget.compute_unit_limit;
+    }
+
+    Ok(tx)
+}
+
+fn read_lookup_indexes(reader: &mut Reader) -> Result<Vec<u8>, &'static str> {
+    let count = reader.read_compact_u16()?;
+    if count > u8::MAX as u16 {
+        return Err("Too many lookup indexes");
+    }
+    Ok(reader.read_bytes(count as usize)?.to_vec())
+}
+
+pub fn decode_bundle<T: AsRef<[u8]>>(
+    transactions: &[T],
+    metadata: BundleMetadata,
+) -> Result<Bundle, &'static str> {
+    let transactions = transactions
+        .iter()
+        .map(|bytes| decode_transaction(bytes.as_ref()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Bundle {
+        transactions,
+        metadata,
+        attestation: None,
+        options: BundleOptions::default(),
+    })
+}
+
+pub fn encode_transaction(tx: &Tx) -> Result<Vec<u8>, &'static str> {
+    let message = encode_message(tx)?;
+
+    let mut out = Vec::with_capacity(3 + tx.signatures.len() * 64 + message.len());
+    encode_len(tx.signatures.len(), &mut out)?;
+    for signature in &tx.signatures {
+        out.extend_from_slice(&signature.bytes);
+    }
+    out.extend_from_slice(&message);
+
+    Ok(out)
+}
+
+// The signed portion of the transaction
+pub fn encode_message(tx: &Tx) -> Result<Vec<u8>, &'static str> {
+    let mut out = Vec::with_capacity(1 + 3 + 3 + tx.account_keys.len() * 32 + 32 + 3);
+    if tx.version != MESSAGE_VERSION_LEGACY {
+        out.push(MESSAGE_VERSION_PREFIX | (tx.version - 1));
+    }
+    out.push(tx.header.num_required_signatures);
+    out.push(tx.header.num_readonly_signed_accounts);
+    out.push(tx.header.num_readonly_unsigned_accounts);
+
+    encode_len(tx.account_keys.len(), &mut out)?;
+    for key in &tx.account_keys {
+        out.extend_from_slice(&key.bytes);
+    }
+
+    out.extend_from_slice(&tx.recent_blockhash);
+
+    encode_len(tx.instructions.len(), &mut out)?;
+    for instruction in &tx.instructions {
+        out.push(instruction.program_id_index);
+        encode_len(instruction.accounts.len(), &mut out)?;
+        out.extend_from_slice(&instruction.accounts);
+        encode_len(instruction.data.len(), &mut out)?;
+        out.extend_from_slice(&instruction.data);
+    }
+
+    if tx.version != MESSAGE_VERSION_LEGACY {
+        encode_len(tx.address_table_lookups.len(), &mut out)?;
+        for lookup in &tx.address_table_lookups {
+            out.extend_from_slice(&lookup.account_key.bytes);
+            encode_len(lookup.writable_indexes.len(), &mut out)?;
+            out.extend_from_slice(&lookup.writable_indexes);
+            encode_len(lookup.readonly_indexes.len(), &mut out)?;
+            out.extend_from_slice(&lookup.readonly_indexes);
+        }
+    }
+
+    Ok(out)
+}
+
+// Lengths go on the wire as compact-u16; anything longer can't be encoded
+fn encode_len(len: usize, out: &mut Vec<u8>) -> Result<(), &'static str> {
+    let len = u16::try_from(len).map_err(|_| "Length exceeds compact-u16 range")?;
+    encode_compact_u16(len, out);
+    Ok(())
+}
+
+// Solana's "shortvec" length prefix: 7 bits per byte, high bit = continuation
+pub fn encode_compact_u16(mut value: u16, out: &mut Vec<u8>) {
+    loop {
+        let mut byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        byte |= 0x80;
+        out.push(byte);
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    fn peek(&self) -> Result<u8, &'static str> {
+        self.data.get(self.offset).copied().ok_or("Unexpected end of data")
+    }
+
+    fn read_u8(&mut self) -> Result<u8, &'static str> {
+        let byte = self.peek()?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        let end = self.offset.checked_add(len).ok_or("Length overflow")?;
+        let bytes = self.data.get(self.offset..end).ok_or("Unexpected end of data")?;
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], &'static str> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+        Ok(array)
+    }
+
+    // Rejects overlong and non-canonical encodings like the runtime does
+    fn read_compact_u16(&mut self) -> Result<u16, &'static str> {
+        let mut value: u32 = 0;
+        for i in 0..3 {
+            let byte = self.read_u8()?;
+            value |= ((byte & 0x7f) as u32) << (i * 7);
+
+            if byte & 0x80 == 0 {
+                if byte == 0 && i > 0 {
+                    return Err("Non-canonical compact-u16 encoding");
+                }
+                if i == 2 && byte > 0x03 {
+                    return Err("Compact-u16 overflow");
+                }
+                return Ok(value as u16);
+            }
+        }
+        Err("Compact-u16 overflow")
+    }
+}